@@ -0,0 +1,385 @@
+//! Vector ASCII log format (`.asc`), e.g.
+//!
+//! ```text
+//! date Thu Jan 1 00:00:00.000 am 1970
+//! base hex  timestamps absolute
+//! internal events logged
+//! Begin Triggerblock Thu Jan 1 00:00:00.000 am 1970
+//!    0.015991 1  123             Rx   d 3 01 02 03
+//!    0.016000 2  18FEF100x       Tx   r 8
+//!    0.020000 CANFD   1 Rx        123                                   1 0 9 12 01 02 03 04 05 06 07 08 09 0A 0B 0C ...
+//! End TriggerBlock
+//! ```
+//!
+//! Only CAN and CAN FD frame lines are handled, other events are skipped.
+
+use core::time::Duration;
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use std::string::String;
+use std::vec::Vec;
+
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+
+pub use crate::can_frame::Direction;
+use crate::can_frame::{CanFrame, FdFlags, FD_LEN};
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("invalid timestamp")]
+    InvalidTimestamp,
+    #[error("invalid channel")]
+    InvalidChannel,
+    #[error("invalid can id")]
+    InvalidId,
+    #[error("invalid data")]
+    InvalidData,
+    #[error("invalid dlc")]
+    InvalidDlc,
+    #[error("io error: {0:?}")]
+    Io(std::io::ErrorKind),
+}
+
+/// one frame of log, `timestamp` & `direction` are also set to `frame`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub timestamp: Duration,
+    pub channel: u8,
+    pub direction: Direction,
+    pub frame: CanFrame,
+}
+
+/// numeric base of id & data, from `base hex|dec' header
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    #[default]
+    Hex,
+    Dec,
+}
+
+impl Base {
+    fn radix(self) -> u32 {
+        match self { Base::Hex => 16, Base::Dec => 10 }
+    }
+}
+
+fn parse_timestamp(s: &str) -> Result<Duration, Error> {
+    let (sec, frac) = s.split_once('.').unwrap_or((s, ""));
+    if frac.len() > 9 { return Err(Error::InvalidTimestamp) }
+
+    let sec: u64 = sec.parse().map_err(|_| Error::InvalidTimestamp)?;
+    let nanos = match frac {
+        "" => 0,
+        f => f.parse::<u32>().map_err(|_| Error::InvalidTimestamp)? * 10u32.pow(9 - f.len() as u32),
+    };
+
+    Ok(Duration::new(sec, nanos))
+}
+
+fn parse_id(s: &str, base: Base) -> Result<Id, Error> {
+    let (s, ext) = match s.strip_suffix('x') {
+        Some(s) => (s, true),
+        None => (s, false),
+    };
+
+    let raw = u32::from_str_radix(s, base.radix()).map_err(|_| Error::InvalidId)?;
+    match ext {
+        true => ExtendedId::new(raw).map(Id::Extended),
+        false => u16::try_from(raw).ok().and_then(StandardId::new).map(Id::Standard),
+    }.ok_or(Error::InvalidId)
+}
+
+fn parse_data<'a>(words: &mut impl Iterator<Item = &'a str>, len: usize, base: Base, buf: &mut [u8; 64]) -> Result<(), Error> {
+    for b in buf.iter_mut().take(len) {
+        let w = words.next().ok_or(Error::InvalidData)?;
+        *b = u8::from_str_radix(w, base.radix()).map_err(|_| Error::InvalidData)?;
+    }
+
+    Ok(())
+}
+
+fn parse_direction(s: &str) -> Option<Direction> {
+    match s {
+        "Rx" => Some(Direction::Rx),
+        "Tx" => Some(Direction::Tx),
+        _ => None,
+    }
+}
+
+// `<time> <channel> <id> <dir> d <dlc> <data>...' or `<time> <channel> <id> <dir> r [<dlc>]'
+fn parse_can<'a>(timestamp: Duration, channel: &str, mut words: impl Iterator<Item = &'a str>, base: Base)
+                 -> Result<Option<Record>, Error> {
+    let Some(id) = words.next() else { return Ok(None) };
+    let Some(direction) = words.next().and_then(parse_direction) else { return Ok(None) };
+    let kind = words.next();
+    if kind != Some("d") && kind != Some("r") { return Ok(None) }
+
+    let channel = channel.parse().map_err(|_| Error::InvalidChannel)?;
+    let id = parse_id(id, base)?;
+
+    let dlc = match words.next() {
+        Some(d) => usize::from_str_radix(d, 16).map_err(|_| Error::InvalidDlc)?,
+        None if kind == Some("r") => 0,
+        None => return Err(Error::InvalidDlc),
+    };
+
+    let frame = if kind == Some("r") {
+        CanFrame::new_remote(id, dlc)
+    } else {
+        let len = dlc.min(8);
+        let mut data = [0u8; 64];
+        parse_data(&mut words, len, base, &mut data)?;
+        CanFrame::new(id, &data[..len])
+    }.ok_or(Error::InvalidDlc)?;
+
+    let frame = frame.with_timestamp(timestamp).with_direction(direction);
+    Ok(Some(Record { timestamp, channel, direction, frame }))
+}
+
+// `<time> CANFD <channel> <dir> <id> [<name>] <brs> <esi> <dlc> <len> <data>... ...'
+fn parse_canfd<'a>(timestamp: Duration, mut words: impl Iterator<Item = &'a str>, base: Base)
+                   -> Result<Option<Record>, Error> {
+    let channel = words.next().ok_or(Error::InvalidChannel)?;
+    let channel = channel.parse().map_err(|_| Error::InvalidChannel)?;
+    let Some(direction) = words.next().and_then(parse_direction) else { return Ok(None) };
+    let id = match words.next() {
+        Some("ErrorFrame") => return Ok(None),
+        id => parse_id(id.ok_or(Error::InvalidId)?, base)?,
+    };
+
+    // symbolic name is optional
+    let rest: Vec<&str> = words.collect();
+    let flag = |s: &str| s == "0" || s == "1";
+    let skip = match rest.as_slice() {
+        [a, b, ..] if flag(a) && flag(b) => 0,
+        _ => 1,
+    };
+    let mut words = rest.into_iter().skip(skip);
+
+    let brs = words.next() == Some("1");
+    let esi = words.next() == Some("1");
+    let dlc = words.next().and_then(|d| usize::from_str_radix(d, 16).ok()).ok_or(Error::InvalidDlc)?;
+    let len: usize = words.next().and_then(|l| l.parse().ok()).ok_or(Error::InvalidDlc)?;
+    if FD_LEN.get(dlc) != Some(&len) { return Err(Error::InvalidDlc) }
+
+    let mut data = [0u8; 64];
+    parse_data(&mut words, len, base, &mut data)?;
+    let frame = CanFrame::new_fd(id, &data[..len], FdFlags { brs, esi }).ok_or(Error::InvalidDlc)?
+        .with_timestamp(timestamp).with_direction(direction);
+
+    Ok(Some(Record { timestamp, channel, direction, frame }))
+}
+
+/// parse one line, `Ok(None)` for line which is not a CAN or CAN FD frame
+pub fn parse_line(line: &str, base: Base) -> Result<Option<Record>, Error> {
+    let mut words = line.split_whitespace();
+
+    let Some(ts) = words.next() else { return Ok(None) };
+    if !ts.starts_with(|c: char| c.is_ascii_digit()) { return Ok(None) }
+    let timestamp = parse_timestamp(ts)?;
+
+    match words.next() {
+        Some("CANFD") => parse_canfd(timestamp, words, base),
+        Some(ch) if ch.bytes().all(|c| c.is_ascii_digit()) => parse_can(timestamp, ch, words, base),
+        _ => Ok(None),
+    }
+}
+
+/// format one line with hex base, reverse of `parse_line`
+pub fn format_line(r: &Record) -> String {
+    let f = &r.frame;
+    let dir = match r.direction { Direction::Rx => "Rx", Direction::Tx => "Tx" };
+    let id = match f.id() {
+        Id::Standard(id) => std::format!("{:X}", id.as_raw()),
+        Id::Extended(id) => std::format!("{:X}x", id.as_raw()),
+    };
+
+    let mut s = std::format!("{:>11}.{:06} ", r.timestamp.as_secs(), r.timestamp.subsec_micros());
+
+    match f.fd_flags() {
+        Some(flags) => {
+            let dlc = FD_LEN.iter().position(|l| *l == f.data().len()).unwrap_or(0);
+            let _ = write!(s, "CANFD {:>3} {:<4} {:>8} {} {} {:x} {:>2}",
+                           r.channel, dir, id, flags.brs as u8, flags.esi as u8, dlc, f.data().len());
+        },
+        None if f.is_remote_frame() => {
+            let _ = write!(s, "{}  {:<15} {:<4} r {:x}", r.channel, id, dir, f.dlc());
+        },
+        None => {
+            let _ = write!(s, "{}  {:<15} {:<4} d {:x}", r.channel, id, dir, f.dlc());
+        },
+    }
+
+    for b in f.data() {
+        let _ = write!(s, " {:02X}", b);
+    }
+
+    s
+}
+
+// ----------------------------------------- reader & writer
+/// iterate over frames of a log, tracking `base' & `timestamps' header,
+/// relative timestamps are accumulated to absolute ones
+pub struct Reader<R: BufRead> {
+    inner: R,
+    line: String,
+    base: Base,
+    relative: bool,
+    /// timestamp of last event, for relative timestamps
+    last: Duration,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Reader { inner, line: String::new(), base: Base::Hex, relative: false, last: Duration::ZERO }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.inner.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {},
+                Err(e) => return Some(Err(Error::Io(e.kind()))),
+            }
+
+            let mut words = self.line.split_whitespace();
+            match words.next() {
+                Some("base") => {
+                    match words.next() {
+                        Some("hex") => self.base = Base::Hex,
+                        Some("dec") => self.base = Base::Dec,
+                        _ => {},
+                    }
+                    if words.next() == Some("timestamps") {
+                        self.relative = words.next() == Some("relative");
+                    }
+                    continue;
+                },
+                // relative to previous event, skipped ones included
+                Some(w) if self.relative => if let Ok(t) = parse_timestamp(w) {
+                    self.last += t;
+                },
+                _ => {},
+            }
+
+            match parse_line(&self.line, self.base) {
+                Ok(None) => continue,
+                Ok(Some(r)) if self.relative => {
+                    let frame = r.frame.with_timestamp(self.last);
+                    return Some(Ok(Record { timestamp: self.last, frame, ..r }));
+                },
+                Ok(Some(r)) => return Some(Ok(r)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// write header on creation, and `End TriggerBlock' on `finish`
+pub struct Writer<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> Writer<W> {
+    /// `date` is the start time of measurement, e.g. `Thu Jan 1 00:00:00.000 am 1970`
+    pub fn new(mut inner: W, date: &str) -> Result<Self, Error> {
+        write!(inner, "date {date}\nbase hex  timestamps absolute\ninternal events logged\nBegin Triggerblock {date}\n")
+            .map_err(|e| Error::Io(e.kind()))?;
+        Ok(Writer { inner })
+    }
+
+    pub fn write(&mut self, r: &Record) -> Result<(), Error> {
+        writeln!(self.inner, "{}", format_line(r)).map_err(|e| Error::Io(e.kind()))
+    }
+
+    pub fn finish(mut self) -> Result<W, Error> {
+        writeln!(self.inner, "End TriggerBlock").map_err(|e| Error::Io(e.kind()))?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOG: &str = "\
+date Thu Jan 1 00:00:00.000 am 1970
+base hex  timestamps absolute
+internal events logged
+// version 9.0.0
+Begin Triggerblock Thu Jan 1 00:00:00.000 am 1970
+   0.000000 Start of measurement
+   0.015991 1  123             Rx   d 3 01 02 03  Length = 240015 BitCount = 124 ID = 291
+   0.016000 2  18FEF100x       Tx   r 8
+   0.017000 1  ErrorFrame
+   0.020000 CANFD   1 Rx        123  Name  1 0 9 12 01 02 03 04 05 06 07 08 09 0A 0B 0C   130000  130   3000 0 0 0 0 0
+End TriggerBlock
+";
+
+    #[test]
+    fn read() {
+        let r: Vec<_> = Reader::new(LOG.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(r.len(), 3);
+
+        assert_eq!(r[0].timestamp, Duration::from_micros(15991));
+        assert_eq!((r[0].channel, r[0].direction), (1, Direction::Rx));
+        assert_eq!(r[0].frame.id(), Id::Standard(StandardId::new(0x123).unwrap()));
+        assert_eq!(r[0].frame.data(), &[1, 2, 3]);
+
+        assert_eq!((r[1].channel, r[1].direction), (2, Direction::Tx));
+        assert!(r[1].frame.is_remote_frame() && r[1].frame.is_extended());
+        assert_eq!(r[1].frame.dlc(), 8);
+
+        assert_eq!(r[2].frame.fd_flags(), Some(FdFlags { brs: true, esi: false }));
+        assert_eq!(r[2].frame.data(), &[1, 2, 3, 4, 5, 6, 7, 8, 9, 0xa, 0xb, 0xc]);
+    }
+
+    #[test]
+    fn relative() {
+        let log = LOG.replace("timestamps absolute", "timestamps relative");
+        let r: Vec<_> = Reader::new(log.as_bytes()).collect::<Result<_, _>>().unwrap();
+        let ts: Vec<_> = r.iter().map(|x| x.timestamp.as_micros()).collect();
+        assert_eq!(ts, [15991, 31991, 68991]);
+        assert!(r.iter().all(|x| x.frame.timestamp() == Some(x.timestamp)));
+    }
+
+    #[test]
+    fn write() {
+        let r: Vec<_> = Reader::new(LOG.as_bytes()).collect::<Result<_, _>>().unwrap();
+
+        let mut w = Writer::new(Vec::new(), "Thu Jan 1 00:00:00.000 am 1970").unwrap();
+        for x in r.iter() {
+            w.write(x).unwrap();
+        }
+        let out = w.finish().unwrap();
+
+        let r2: Vec<_> = Reader::new(out.as_slice()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(r, r2);
+    }
+
+    #[test]
+    fn dec() {
+        let r = parse_line("1.5 1 291 Rx d 2 1 255", Base::Dec).unwrap().unwrap();
+        assert_eq!(r.frame.id(), Id::Standard(StandardId::new(0x123).unwrap()));
+        assert_eq!(r.frame.data(), &[1, 255]);
+    }
+
+    #[test]
+    fn err() {
+        assert_eq!(parse_line("1.0 1 800 Rx d 0", Base::Hex), Err(Error::InvalidId));
+        assert_eq!(parse_line("1.0 1 123 Rx d 2 01", Base::Hex), Err(Error::InvalidData));
+        assert_eq!(parse_line("1.0 CANFD 1 Rx 123 0 0 9 11", Base::Hex), Err(Error::InvalidDlc));
+        assert_eq!(parse_line("1.0 1 Statistic: D 0 R 0", Base::Hex), Ok(None));
+    }
+
+    #[test]
+    fn decode() {
+        let r = parse_line("1.0 1 123 Rx d 2 30 39", Base::Hex).unwrap().unwrap();
+        assert_eq!(crate::from_frame::<u16, _>(&r.frame), Ok(12345));
+    }
+}
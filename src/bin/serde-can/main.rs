@@ -0,0 +1,369 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use embedded_can::{Frame, Id};
+use serde_can::{asc, candump, can_frame::CanFrame};
+
+mod schema;
+use schema::{parse_u32, Schema, Value};
+
+const USAGE: &str = "\
+usage: serde-can [options] [LOG]
+
+decode CAN frames read from LOG (or stdin), one frame per line, in
+`candump -L' format (`(1.000000) can0 123#DEADBEEF') or bare `123#DEADBEEF',
+or Vector ASC format if LOG end with `.asc' or `--asc' is given.
+
+options:
+  -s, --schema FILE    schema of messages, keyed by msg_id
+  -j, --json           print one JSON object per frame instead of a table
+  --asc                input is Vector ASC log
+  --base BASE          NodeGroup BASE, split id into node_id/msg_id
+  --node-bits N        NodeGroup NODE_ID_LEN
+  --msg-bits N         NodeGroup MSG_ID_LEN
+  -h, --help           show this help
+";
+
+#[derive(Debug, Clone, Copy)]
+struct Layout { base: u32, node_id_len: usize, msg_id_len: usize }
+
+impl Layout {
+    // same split as `NodeGroup::extract'
+    fn split(&self, id: u32) -> Option<(u32, u32)> {
+        let msg_id_mask = (1u32 << self.msg_id_len) - 1;
+        let node_id_mask = ((1u32 << self.node_id_len) - 1) << self.msg_id_len;
+        let base_mask = !(msg_id_mask | node_id_mask);
+
+        if id & base_mask != self.base { return None }
+        Some(((id & node_id_mask) >> self.msg_id_len, id & msg_id_mask))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Opts {
+    schema: Option<String>,
+    json: bool,
+    log: Option<String>,
+    asc: bool,
+    base: Option<u32>,
+    node_id_len: Option<usize>,
+    msg_id_len: Option<usize>,
+}
+
+fn parse_args() -> Result<Opts, String> {
+    let mut opts = Opts::default();
+    let mut args = std::env::args().skip(1);
+
+    while let Some(a) = args.next() {
+        let mut val = |name: &str| args.next().ok_or_else(|| format!("{} need a value", name));
+        match a.as_str() {
+            "-h" | "--help" => { print!("{}", USAGE); std::process::exit(0) },
+            "-s" | "--schema" => opts.schema = Some(val(&a)?),
+            "-j" | "--json" => opts.json = true,
+            "--asc" => opts.asc = true,
+            "--base" => opts.base = Some(parse_u32(&val(&a)?).ok_or("invalid --base")?),
+            "--node-bits" => opts.node_id_len = Some(val(&a)?.parse().map_err(|_| "invalid --node-bits")?),
+            "--msg-bits" => opts.msg_id_len = Some(val(&a)?.parse().map_err(|_| "invalid --msg-bits")?),
+            _ if a.starts_with('-') => return Err(format!("unknown option {}", a)),
+            _ => opts.log = Some(a),
+        }
+    }
+
+    Ok(opts)
+}
+
+impl Opts {
+    fn is_asc(&self) -> bool {
+        self.asc || self.log.as_deref().is_some_and(|p| p.to_ascii_lowercase().ends_with(".asc"))
+    }
+
+    fn layout(&self) -> Result<Option<Layout>, String> {
+        let Some(base) = self.base else {
+            if self.node_id_len.is_some() || self.msg_id_len.is_some() {
+                return Err("--node-bits & --msg-bits need --base".into());
+            }
+            return Ok(None)
+        };
+        let (node_id_len, msg_id_len) = (self.node_id_len.unwrap_or(0), self.msg_id_len.unwrap_or(0));
+
+        // same check as `NodeGroup::new'
+        let len = node_id_len.checked_add(msg_id_len).filter(|x| *x <= 29)
+            .ok_or("sum of --node-bits & --msg-bits too large")?;
+        if base & !(!0u32 << len) != 0 {
+            return Err("msg_id part & node_id part of --base should be 0".into());
+        }
+        if base & 0xe000_0000 != 0 {
+            return Err("--base length > 29".into());
+        }
+
+        Ok(Some(Layout { base, node_id_len, msg_id_len }))
+    }
+}
+
+// ----------------------------------------- input
+#[derive(Debug, PartialEq)]
+struct Record {
+    timestamp: Option<String>,
+    interface: Option<String>,
+    id: u32,
+    extended: bool,
+    remote: bool,
+    data: Vec<u8>,
+}
+
+impl From<CanFrame> for Record {
+    fn from(f: CanFrame) -> Self {
+        let (id, extended) = match f.id() {
+            Id::Standard(id) => (id.as_raw() as u32, false),
+            Id::Extended(id) => (id.as_raw(), true),
+        };
+        let data = if f.is_remote_frame() { vec![0; f.dlc()] } else { f.data().to_vec() };
+
+        Record { timestamp: None, interface: None, id, extended, remote: f.is_remote_frame(), data }
+    }
+}
+
+impl From<asc::Record> for Record {
+    fn from(r: asc::Record) -> Self {
+        let timestamp = format!("{}.{:06}", r.timestamp.as_secs(), r.timestamp.subsec_micros());
+        Record { timestamp: Some(timestamp), interface: Some(r.channel.to_string()), ..r.frame.into() }
+    }
+}
+
+// `candump -L' line, or bare frame in `cansend' syntax
+fn parse_line(line: &str) -> Option<Record> {
+    if let Ok(r) = candump::parse_line(line) {
+        let timestamp = format!("{}.{:06}", r.timestamp.as_secs(), r.timestamp.subsec_micros());
+        return Some(Record { timestamp: Some(timestamp), interface: Some(r.interface), ..r.frame.into() });
+    }
+
+    candump::parse_frame(line.trim()).ok().map(Record::from)
+}
+
+// ----------------------------------------- output
+struct Row {
+    record: Record,
+    split: Option<(u32, u32)>,
+    name: Option<String>,
+    value: Result<Vec<(String, Value)>, String>,
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut s, b| { let _ = write!(s, "{:02X}", b); s })
+}
+
+fn fmt_id(r: &Record) -> String {
+    if r.extended { format!("{:08X}", r.id) } else { format!("{:03X}", r.id) }
+}
+
+fn fmt_value(v: &Value, json: bool) -> String {
+    match v {
+        Value::Bool(b)  => b.to_string(),
+        Value::Int(i)   => i.to_string(),
+        Value::UInt(u)  => u.to_string(),
+        Value::Float(f) if json && !f.is_finite() => "null".into(),
+        Value::Float(f) => f.to_string(),
+        Value::Str(s) if json => json_str(s),
+        Value::Str(s)   => format!("{:?}", s),
+        Value::Bytes(b) if json => json_str(&hex(b)),
+        Value::Bytes(b) => hex(b),
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut r = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            c if (c as u32) < 0x20 => { let _ = write!(r, "\\u{:04x}", c as u32); },
+            c => r.push(c),
+        }
+    }
+    r.push('"');
+    r
+}
+
+fn print_json(out: &mut impl Write, row: &Row) -> io::Result<()> {
+    let r = &row.record;
+    let mut s = String::from("{");
+
+    if let Some(t) = &r.timestamp {
+        let t = if t.parse::<f64>().is_ok() { t.clone() } else { json_str(t) };
+        let _ = write!(s, "\"timestamp\":{},", t);
+    }
+    if let Some(i) = &r.interface { let _ = write!(s, "\"interface\":{},", json_str(i)); }
+    let _ = write!(s, "\"id\":{},\"extended\":{},\"remote\":{}", json_str(&fmt_id(r)), r.extended, r.remote);
+
+    if let Some((node_id, msg_id)) = row.split {
+        let _ = write!(s, ",\"node_id\":{},\"msg_id\":{}", node_id, msg_id);
+    }
+    if let Some(name) = &row.name {
+        let _ = write!(s, ",\"name\":{}", json_str(name));
+    }
+
+    match &row.value {
+        Ok(fields) if row.name.is_some() => {
+            let fields: Vec<_> = fields.iter()
+                .map(|(k, v)| format!("{}:{}", json_str(k), fmt_value(v, true)))
+                .collect();
+            let _ = write!(s, ",\"fields\":{{{}}}", fields.join(","));
+        },
+        Ok(_) => { let _ = write!(s, ",\"data\":{}", json_str(&hex(&r.data))); },
+        Err(e) => { let _ = write!(s, ",\"data\":{},\"error\":{}", json_str(&hex(&r.data)), json_str(e)); },
+    }
+
+    writeln!(out, "{}}}", s)
+}
+
+fn print_table(out: &mut impl Write, row: &Row) -> io::Result<()> {
+    let r = &row.record;
+    let (node_id, msg_id) = match row.split {
+        Some((n, m)) => (n.to_string(), m.to_string()),
+        None => ("-".into(), "-".into()),
+    };
+
+    let value = match &row.value {
+        _ if r.remote => format!("R{}", r.data.len()),
+        Ok(fields) if row.name.is_some() => fields.iter()
+            .map(|(k, v)| format!("{}={}", k, fmt_value(v, false)))
+            .collect::<Vec<_>>()
+            .join(" "),
+        Ok(_) => hex(&r.data),
+        Err(e) => format!("{} ({})", hex(&r.data), e),
+    };
+
+    writeln!(out, "{:<18} {:<6} {:>8} {:>5} {:>5} {:<12} {}",
+             r.timestamp.as_deref().unwrap_or("-"),
+             r.interface.as_deref().unwrap_or("-"),
+             fmt_id(r), node_id, msg_id,
+             row.name.as_deref().unwrap_or("-"),
+             value)
+}
+
+fn run(opts: &Opts) -> Result<(), String> {
+    let layout = opts.layout()?;
+    let schema = match &opts.schema {
+        Some(p) => {
+            let text = std::fs::read_to_string(p).map_err(|e| format!("{}: {}", p, e))?;
+            Schema::parse(&text)?
+        },
+        None => Schema::default(),
+    };
+
+    let input: Box<dyn BufRead> = match &opts.log {
+        Some(p) => Box::new(io::BufReader::new(
+            std::fs::File::open(p).map_err(|e| format!("{}: {}", p, e))?)),
+        None => Box::new(io::stdin().lock()),
+    };
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+
+    if !opts.json {
+        writeln!(out, "{:<18} {:<6} {:>8} {:>5} {:>5} {:<12} VALUE",
+                 "TIMESTAMP", "IFACE", "ID", "NODE", "MSG", "NAME").map_err(|e| e.to_string())?;
+    }
+
+    // `Ok(None)' for skipped line
+    let records: Box<dyn Iterator<Item = Result<Option<Record>, String>>> = if opts.is_asc() {
+        Box::new(asc::Reader::new(input).map(|r| match r {
+            Ok(r) => Ok(Some(r.into())),
+            Err(asc::Error::Io(e)) => Err(io::Error::from(e).to_string()),
+            Err(e) => { eprintln!("skip invalid line: {}", e); Ok(None) },
+        }))
+    } else {
+        Box::new(input.lines().map(|line| {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() { return Ok(None) }
+
+            let r = parse_line(&line);
+            if r.is_none() { eprintln!("skip invalid line: {}", line); }
+            Ok(r)
+        }))
+    };
+
+    for record in records {
+        let Some(record) = record? else { continue };
+
+        // without layout, whole CAN id is used as msg_id
+        let split = match layout {
+            Some(l) => l.split(record.id),
+            None => None,
+        };
+        let msg_id = match (layout, split) {
+            (None, _) => Some(record.id),
+            (_, s) => s.map(|(_, m)| m),
+        };
+
+        let msg = msg_id.and_then(|m| schema.get(m)).filter(|_| !record.remote);
+        let value = match msg {
+            Some(m) => m.decode(&record.data).map_err(|e| e.to_string()),
+            None => Ok(Vec::new()),
+        };
+
+        let row = Row { name: msg.map(|m| m.name.clone()), record, split, value };
+        let res = if opts.json { print_json(&mut out, &row) } else { print_table(&mut out, &row) };
+
+        match res {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            r => r.map_err(|e| e.to_string())?,
+        }
+    }
+
+    out.flush().map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let res = parse_args().and_then(|opts| run(&opts));
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("serde-can: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line() {
+        let r = parse_line("(1436509052.249713) vcan0 044#2A366C2BBA").unwrap();
+        assert_eq!(r.timestamp.as_deref(), Some("1436509052.249713"));
+        assert_eq!(r.interface.as_deref(), Some("vcan0"));
+        assert_eq!((r.id, r.extended, r.remote), (0x44, false, false));
+        assert_eq!(r.data, [0x2a, 0x36, 0x6c, 0x2b, 0xba]);
+
+        let r = parse_line("12345678#R3").unwrap();
+        assert_eq!((r.id, r.extended, r.remote, r.data.len()), (0x1234_5678, true, true, 3));
+
+        let r = parse_line("123##1112233").unwrap();
+        assert_eq!(r.data, [0x11, 0x22, 0x33]);
+
+        assert_eq!(parse_line("123#1"), None);
+        assert_eq!(parse_line("vcan0"), None);
+    }
+
+    #[test]
+    fn split() {
+        let l = Layout { base: 0x1_9876_540, node_id_len: 3, msg_id_len: 3 };
+        assert_eq!(l.split(0x1_9876_540 | (5 << 3) | 2), Some((5, 2)));
+        assert_eq!(l.split(0x1_1234_560), None);
+    }
+
+    #[test]
+    fn layout() {
+        let opts = Opts { node_id_len: Some(3), ..Default::default() };
+        assert!(opts.layout().is_err());
+        let opts = Opts { base: Some(0), node_id_len: Some(usize::MAX), msg_id_len: Some(1), ..Default::default() };
+        assert!(opts.layout().is_err());
+        let opts = Opts { base: Some(0x1_9876_540), node_id_len: Some(3), msg_id_len: Some(3), ..Default::default() };
+        assert_eq!(opts.layout().unwrap().map(|l| l.node_id_len), Some(3));
+        assert_eq!(Opts::default().layout().map(|l| l.is_none()), Ok(true));
+    }
+}
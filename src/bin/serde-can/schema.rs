@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use serde_can::{Deserializer, Error};
+
+/* schema file, one message per line, `#' start a comment:
+
+   <msg_id> <name> [<field>:<type>]...
+
+   e.g.  `1 status mode:u8 enabled:bool temp:i16'
+ */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Bool, U8, I8, U16, I16, U32, I32, U64, I64, F32, F64, Char, Str, Bytes,
+}
+
+impl Type {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "bool"  => Type::Bool,
+            "u8"    => Type::U8,
+            "i8"    => Type::I8,
+            "u16"   => Type::U16,
+            "i16"   => Type::I16,
+            "u32"   => Type::U32,
+            "i32"   => Type::I32,
+            "u64"   => Type::U64,
+            "i64"   => Type::I64,
+            "f32"   => Type::F32,
+            "f64"   => Type::F64,
+            "char"  => Type::Char,
+            "str"   => Type::Str,
+            "bytes" => Type::Bytes,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub struct Msg {
+    pub name: String,
+    pub fields: Vec<(String, Type)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Schema {
+    msgs: BTreeMap<u32, Msg>,
+}
+
+impl Schema {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut msgs = BTreeMap::new();
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue }
+
+            let mut words = line.split_whitespace();
+            let err = |e: &str| format!("schema line {}: {}", n + 1, e);
+
+            let msg_id = words.next()
+                .and_then(parse_u32)
+                .ok_or_else(|| err("invalid msg_id"))?;
+            let name = words.next().ok_or_else(|| err("missing name"))?.to_string();
+
+            let mut fields = Vec::new();
+            for w in words {
+                let Some((f, t)) = w.split_once(':') else {
+                    return Err(err("field should be <name>:<type>"));
+                };
+                let t = Type::parse(t).ok_or_else(|| err("unknown type"))?;
+                fields.push((f.to_string(), t));
+            }
+
+            if msgs.insert(msg_id, Msg { name, fields }).is_some() {
+                return Err(err("duplicated msg_id"));
+            }
+        }
+
+        Ok(Schema { msgs })
+    }
+
+    pub fn get(&self, msg_id: u32) -> Option<&Msg> {
+        self.msgs.get(&msg_id)
+    }
+}
+
+impl Msg {
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<(String, Value)>, Error> {
+        let mut de = Deserializer::from_bytes(data);
+        let mut res = Vec::new();
+
+        for (name, tp) in self.fields.iter() {
+            de.check_len()?;
+            let v = match tp {
+                Type::Bool  => Value::Bool(de.dec_bool()?),
+                Type::U8    => Value::UInt(de.dec_u8()? as u64),
+                Type::I8    => Value::Int(de.dec_u8()? as i8 as i64),
+                Type::U16   => Value::UInt(de.dec_u16()? as u64),
+                Type::I16   => Value::Int(de.dec_u16()? as i16 as i64),
+                Type::U32   => Value::UInt(de.dec_u32()? as u64),
+                Type::I32   => Value::Int(de.dec_u32()? as i32 as i64),
+                Type::U64   => Value::UInt(de.dec_u64()?),
+                Type::I64   => Value::Int(de.dec_u64()? as i64),
+                Type::F32   => Value::Float(f32::from_bits(de.dec_u32()?) as f64),
+                Type::F64   => Value::Float(f64::from_bits(de.dec_u64()?)),
+                Type::Char | Type::Str => Value::Str(de.dec_str()?.to_string()),
+                Type::Bytes => Value::Bytes(de.dec_bytes()?.to_vec()),
+            };
+
+            res.push((name.clone(), v));
+        }
+
+        de.check_len()?;
+        Ok(res)
+    }
+}
+
+pub fn parse_u32(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(h) => u32::from_str_radix(h, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let s = Schema::parse("# comment\n0x1 status mode:u8 on:bool t:i16\n").unwrap();
+        let m = s.get(1).unwrap();
+        assert_eq!(m.name, "status");
+
+        let data = serde_can::to_bytes(&(7u8, true, -2i16)).unwrap();
+        let v = m.decode(&data).unwrap();
+        assert_eq!(v[0], ("mode".to_string(), Value::UInt(7)));
+        assert_eq!(v[1], ("on".to_string(), Value::Bool(true)));
+        assert_eq!(v[2], ("t".to_string(), Value::Int(-2)));
+
+        assert_eq!(m.decode(&[7]), Err(Error::DeMsgTooLong));
+    }
+
+    #[test]
+    fn parse_err() {
+        assert!(Schema::parse("x status").is_err());
+        assert!(Schema::parse("1 status a:u7").is_err());
+        assert!(Schema::parse("1 a\n1 b").is_err());
+    }
+}
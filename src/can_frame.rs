@@ -0,0 +1,197 @@
+use core::fmt;
+use core::time::Duration;
+
+use embedded_can::{Frame, Id};
+
+/// flags of CAN FD frame
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FdFlags {
+    /// bit rate switch
+    pub brs: bool,
+    /// error state indicator
+    pub esi: bool,
+}
+
+/// frame is received or transmitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Rx,
+    Tx,
+}
+
+/// classic CAN or CAN FD frame, with up to 64 bytes of data
+///
+/// `Display` & `Debug` use `candump` syntax, e.g. `123#DEADBEEF`, `12345678#R4`,
+/// or `123##1DEADBEEF` for CAN FD frame.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CanFrame {
+    id: Id,
+    data: [u8; 64],
+    len: u8,
+    remote: bool,
+    fd: Option<FdFlags>,
+    timestamp: Option<Duration>,
+    direction: Option<Direction>,
+}
+
+/// valid data length of CAN FD frame, index is the DLC
+pub(crate) const FD_LEN: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+impl CanFrame {
+    fn build(id: Id, data: &[u8], remote: bool, fd: Option<FdFlags>) -> Self {
+        let mut res_data = [0u8; 64];
+        res_data[..data.len()].copy_from_slice(data);
+
+        CanFrame { id, data: res_data, len: data.len() as u8, remote, fd, timestamp: None, direction: None }
+    }
+
+    /// create CAN FD frame, `None` if length of `data` is not a valid FD length
+    pub fn new_fd(id: impl Into<Id>, data: &[u8], flags: FdFlags) -> Option<Self> {
+        if !FD_LEN.contains(&data.len()) { return None }
+        Some(Self::build(id.into(), data, false, Some(flags)))
+    }
+
+    /// `None` for classic CAN frame
+    pub fn fd_flags(&self) -> Option<FdFlags> { self.fd }
+
+    pub fn is_fd(&self) -> bool { self.fd.is_some() }
+
+    pub fn timestamp(&self) -> Option<Duration> { self.timestamp }
+
+    pub fn direction(&self) -> Option<Direction> { self.direction }
+
+    pub fn with_timestamp(mut self, timestamp: Duration) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+}
+
+impl Frame for CanFrame {
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        if data.len() > 8 { return None }
+        Some(Self::build(id.into(), data, false, None))
+    }
+
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        if dlc > 8 { return None }
+
+        let mut f = Self::build(id.into(), &[], true, None);
+        f.len = dlc as u8;
+        Some(f)
+    }
+
+    fn is_extended(&self) -> bool { matches!(self.id, Id::Extended(_)) }
+    fn is_remote_frame(&self) -> bool { self.remote }
+    fn id(&self) -> Id { self.id }
+
+    /// for CAN FD frame, this is the data length in bytes, not the DLC code
+    fn dlc(&self) -> usize { self.len as usize }
+
+    fn data(&self) -> &[u8] {
+        if self.remote { return &[] }
+        &self.data[..self.len as usize]
+    }
+}
+
+impl fmt::Display for CanFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.id {
+            Id::Standard(id) => write!(f, "{:03X}#", id.as_raw())?,
+            Id::Extended(id) => write!(f, "{:08X}#", id.as_raw())?,
+        }
+
+        if self.remote {
+            return write!(f, "R{}", self.len);
+        }
+
+        if let Some(flags) = self.fd {
+            write!(f, "#{:X}", (flags.brs as u8) | ((flags.esi as u8) << 1))?;
+        }
+
+        for b in self.data() {
+            write!(f, "{:02X}", b)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `candump -l` like, e.g. `(1.000000) 123#DEADBEEF Rx`
+impl fmt::Debug for CanFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ts) = self.timestamp {
+            write!(f, "({}.{:06}) ", ts.as_secs(), ts.subsec_micros())?;
+        }
+
+        write!(f, "{}", self)?;
+
+        match self.direction {
+            Some(d) => write!(f, " {:?}", d),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use embedded_can::{ExtendedId, StandardId};
+
+    #[test]
+    fn classic() {
+        let id = StandardId::new(0x123).unwrap();
+        let f = CanFrame::new(id, &[1, 2, 3]).unwrap();
+        assert_eq!(f.data(), &[1, 2, 3]);
+        assert_eq!(f.dlc(), 3);
+        assert!(f.is_standard() && !f.is_fd());
+
+        assert_eq!(CanFrame::new(id, &[0; 9]), None);
+
+        let f = CanFrame::new_remote(id, 4).unwrap();
+        assert!(f.is_remote_frame());
+        assert_eq!((f.dlc(), f.data()), (4, &[][..]));
+    }
+
+    #[test]
+    fn fd() {
+        let id = ExtendedId::new(0x1234_5678).unwrap();
+        let flags = FdFlags { brs: true, esi: false };
+        let f = CanFrame::new_fd(id, &[0xaa; 12], flags).unwrap();
+        assert_eq!(f.data(), &[0xaa; 12]);
+        assert_eq!(f.fd_flags(), Some(flags));
+        assert!(f.is_extended());
+
+        assert_eq!(CanFrame::new_fd(id, &[0; 9], flags), None);
+    }
+
+    #[test]
+    fn fmt() {
+        extern crate std;
+        use std::format;
+
+        let id = StandardId::new(0x123).unwrap();
+        let f = CanFrame::new(id, &[0xde, 0xad]).unwrap();
+        assert_eq!(format!("{}", f), "123#DEAD");
+
+        let f = f.with_timestamp(Duration::from_millis(1500)).with_direction(Direction::Tx);
+        assert_eq!(format!("{:?}", f), "(1.500000) 123#DEAD Tx");
+
+        let id = ExtendedId::new(0x1234_5678).unwrap();
+        assert_eq!(format!("{}", CanFrame::new_remote(id, 4).unwrap()), "12345678#R4");
+
+        let f = CanFrame::new_fd(id, &[1; 12], FdFlags { brs: true, esi: true }).unwrap();
+        assert_eq!(format!("{}", f), "12345678##3010101010101010101010101");
+    }
+
+    #[test]
+    fn endec() {
+        let id = StandardId::new(0x123).unwrap();
+        let f: CanFrame = crate::to_frame(id, &(-1i8, 0x1234u16)).unwrap();
+        assert_eq!(crate::from_frame::<(i8, u16), _>(&f), Ok((-1, 0x1234)));
+    }
+}
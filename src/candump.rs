@@ -0,0 +1,221 @@
+//! Linux `candump -L` log format, e.g.
+//!
+//! ```text
+//! (1436509052.249713) vcan0 044#2A366C2BBA
+//! (1436509052.449847) vcan0 12345678#R4
+//! (1436509052.650004) can1 123##1112233445566778899AABBCC
+//! ```
+
+use core::time::Duration;
+use std::io::{BufRead, Write};
+use std::string::{String, ToString};
+
+use embedded_can::{ExtendedId, Frame, Id, StandardId};
+
+use crate::can_frame::{CanFrame, FdFlags};
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("invalid timestamp")]
+    InvalidTimestamp,
+    #[error("missing interface")]
+    MissingInterface,
+    #[error("invalid can id")]
+    InvalidId,
+    #[error("invalid data")]
+    InvalidData,
+    #[error("invalid dlc")]
+    InvalidDlc,
+    #[error("io error: {0:?}")]
+    Io(std::io::ErrorKind),
+}
+
+/// one line of log, `timestamp` is also set to `frame`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub timestamp: Duration,
+    pub interface: String,
+    pub frame: CanFrame,
+}
+
+fn hex_digit(c: u8) -> Result<u8, Error> {
+    (c as char).to_digit(16).map(|d| d as u8).ok_or(Error::InvalidData)
+}
+
+/// parse frame in `cansend` syntax, `<can_id>#<data>`, `<can_id>#R<len>` or
+/// `<can_id>##<flags><data>`
+pub fn parse_frame(s: &str) -> Result<CanFrame, Error> {
+    let (id_s, data_s) = s.split_once('#').ok_or(Error::InvalidId)?;
+
+    if !id_s.bytes().all(|c| c.is_ascii_hexdigit()) { return Err(Error::InvalidId) }
+    let id_raw = u32::from_str_radix(id_s, 16).map_err(|_| Error::InvalidId)?;
+    let id: Id = match id_s.len() {
+        3 => StandardId::new(id_raw as u16).ok_or(Error::InvalidId)?.into(),
+        8 => ExtendedId::new(id_raw).ok_or(Error::InvalidId)?.into(),
+        _ => return Err(Error::InvalidId),
+    };
+
+    if let Some(len) = data_s.strip_prefix('R') {
+        // `_<dlc>' of len8_dlc is ignored
+        let len = len.split('_').next().unwrap_or("");
+        let dlc = if len.is_empty() { 0 } else { len.parse().map_err(|_| Error::InvalidDlc)? };
+        return CanFrame::new_remote(id, dlc).ok_or(Error::InvalidDlc);
+    }
+
+    let (fd, data_s) = match data_s.strip_prefix('#') {
+        Some(d) => {
+            let flags = hex_digit(*d.as_bytes().first().ok_or(Error::InvalidData)?)?;
+            (Some(FdFlags { brs: flags & 1 != 0, esi: flags & 2 != 0 }), &d[1..])
+        },
+        None => (None, data_s.split('_').next().unwrap_or("")),
+    };
+
+    let mut data = [0u8; 64];
+    let mut len = 0;
+    let mut digits = data_s.bytes().filter(|c| *c != b'.');
+    while let Some(h) = digits.next() {
+        let Some(l) = digits.next() else { return Err(Error::InvalidData) };
+        if len == data.len() { return Err(Error::InvalidDlc) }
+        data[len] = (hex_digit(h)? << 4) | hex_digit(l)?;
+        len += 1;
+    }
+
+    match fd {
+        Some(flags) => CanFrame::new_fd(id, &data[..len], flags),
+        None => CanFrame::new(id, &data[..len]),
+    }.ok_or(Error::InvalidDlc)
+}
+
+/// format frame in `cansend` syntax, reverse of `parse_frame`
+pub fn format_frame(f: &CanFrame) -> String {
+    f.to_string()
+}
+
+pub fn parse_line(line: &str) -> Result<Record, Error> {
+    let mut words = line.split_whitespace();
+
+    let ts = words.next()
+        .and_then(|t| t.strip_prefix('('))
+        .and_then(|t| t.strip_suffix(')'))
+        .ok_or(Error::InvalidTimestamp)?;
+    let (sec, usec) = ts.split_once('.').ok_or(Error::InvalidTimestamp)?;
+    if usec.is_empty() || usec.len() > 9 { return Err(Error::InvalidTimestamp) }
+    let sec: u64 = sec.parse().map_err(|_| Error::InvalidTimestamp)?;
+    let frac: u32 = usec.parse().map_err(|_| Error::InvalidTimestamp)?;
+    let nanos = frac * 10u32.pow(9 - usec.len() as u32);
+    let timestamp = Duration::new(sec, nanos);
+
+    let interface = words.next().ok_or(Error::MissingInterface)?.to_string();
+    let frame = parse_frame(words.next().ok_or(Error::InvalidId)?)?.with_timestamp(timestamp);
+
+    Ok(Record { timestamp, interface, frame })
+}
+
+pub fn format_line(r: &Record) -> String {
+    std::format!("({:010}.{:06}) {} {}",
+                 r.timestamp.as_secs(), r.timestamp.subsec_micros(),
+                 r.interface, format_frame(&r.frame))
+}
+
+// ----------------------------------------- reader & writer
+/// iterate over records of a log, empty lines are skipped
+pub struct Reader<R: BufRead> {
+    inner: R,
+    line: String,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Reader { inner, line: String::new() }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.inner.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) if self.line.trim().is_empty() => continue,
+                Ok(_) => return Some(parse_line(&self.line)),
+                Err(e) => return Some(Err(Error::Io(e.kind()))),
+            }
+        }
+    }
+}
+
+pub struct Writer<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(inner: W) -> Self {
+        Writer { inner }
+    }
+
+    pub fn write(&mut self, r: &Record) -> Result<(), Error> {
+        writeln!(self.inner, "{}", format_line(r)).map_err(|e| Error::Io(e.kind()))
+    }
+
+    pub fn into_inner(self) -> W { self.inner }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec::Vec;
+
+    const LOG: &str = "\
+(1436509052.249713) vcan0 044#2A366C2BBA
+(1436509052.449847) vcan0 12345678#R4
+
+(1436509052.650004) can1 123##1112233445566778899AABBCC
+";
+
+    #[test]
+    fn read() {
+        let r: Vec<_> = Reader::new(LOG.as_bytes()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(r.len(), 3);
+
+        assert_eq!(r[0].timestamp, Duration::new(1436509052, 249_713_000));
+        assert_eq!(r[0].interface, "vcan0");
+        assert_eq!(r[0].frame.id(), Id::Standard(StandardId::new(0x44).unwrap()));
+        assert_eq!(r[0].frame.data(), &[0x2a, 0x36, 0x6c, 0x2b, 0xba]);
+
+        assert!(r[1].frame.is_remote_frame() && r[1].frame.is_extended());
+        assert_eq!(r[1].frame.dlc(), 4);
+
+        assert_eq!(r[2].frame.fd_flags(), Some(FdFlags { brs: true, esi: false }));
+        assert_eq!(r[2].frame.data().len(), 12);
+    }
+
+    #[test]
+    fn write() {
+        let mut w = Writer::new(Vec::new());
+        for r in Reader::new(LOG.as_bytes()) {
+            w.write(&r.unwrap()).unwrap();
+        }
+
+        let out = String::from_utf8(w.into_inner()).unwrap();
+        assert_eq!(out, LOG.replace("\n\n", "\n"));
+    }
+
+    #[test]
+    fn err() {
+        assert_eq!(parse_line("1.0 can0 123#00"), Err(Error::InvalidTimestamp));
+        assert_eq!(parse_line("(1.0)"), Err(Error::MissingInterface));
+        assert_eq!(parse_frame("1234#00"), Err(Error::InvalidId));
+        assert_eq!(parse_frame("800#00"), Err(Error::InvalidId));
+        assert_eq!(parse_frame("123#0"), Err(Error::InvalidData));
+        assert_eq!(parse_frame("123#001122334455667788"), Err(Error::InvalidDlc));
+        assert_eq!(parse_frame("123##1001122334455667788"), Err(Error::InvalidDlc));
+    }
+
+    #[test]
+    fn decode() {
+        let r = parse_line("(0.000001) can0 00000123#3039").unwrap();
+        assert_eq!(crate::from_frame::<u16, _>(&r.frame), Ok(12345));
+    }
+}
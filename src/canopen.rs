@@ -0,0 +1,595 @@
+//! CANopen predefined connection set: PDO, NMT, heartbeat & expedited SDO
+//!
+//! ```text
+//! | function code | node id |   -> 11 bits COB-ID
+//!        4             7
+//! ```
+//!
+//! **Byte order**: PDO & SDO values are serde_can-encoded, i.e. bit-packed
+//! big-endian, while CiA 301 objects are little-endian, e.g. `0x1234u16` is
+//! `12 34` here but `34 12` in the object dictionary of a standard device.
+//! So serde_can values only interoperate between serde_can peers; for standard
+//! devices, use the `*_cia` variants with a `CiaValue`, which is little-endian
+//! & byte-aligned as CiA 301.
+
+use embedded_can::{Frame, Id, StandardId};
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::{from_bytes, from_frame, to_bytes, to_frame};
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("node id {0} not in 1..=127")]
+    InvalidNodeId(u8),
+    #[error("pdo number {0} not in 1..=4")]
+    InvalidPdo(u8),
+    #[error("unexpected cob-id {0:#x}")]
+    UnexpectedCobId(u32),
+    #[error("invalid nmt/heartbeat/sdo payload")]
+    InvalidPayload,
+    #[error("sdo value of {0} bytes too long for expedited transfer")]
+    SdoValueTooLong(usize),
+    #[error("empty sdo value, expedited transfer carries 1 to 4 bytes")]
+    SdoValueEmpty,
+    #[error("frame error: {0}")]
+    FrameErr(crate::frame::Error),
+    #[error("serde error: {0}")]
+    SerdeErr(crate::Error),
+}
+
+impl From<crate::frame::Error> for Error {
+    fn from(e: crate::frame::Error) -> Self {
+        match e {
+            crate::frame::Error::SerdeErr(e) => Error::SerdeErr(e),
+            _ => Error::FrameErr(e),
+        }
+    }
+}
+
+/// communication object of the predefined connection set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Nmt,
+    Sync,
+    Emcy,
+    /// transmit PDO 1..=4
+    Tpdo(u8),
+    /// receive PDO 1..=4
+    Rpdo(u8),
+    /// SDO server to client
+    SdoTx,
+    /// SDO client to server
+    SdoRx,
+    Heartbeat,
+}
+
+impl Function {
+    fn base(&self) -> u16 {
+        match *self {
+            Function::Nmt => 0x000,
+            Function::Sync | Function::Emcy => 0x080,
+            Function::Tpdo(n) => 0x080 + 0x100 * n as u16,
+            Function::Rpdo(n) => 0x100 + 0x100 * n as u16,
+            Function::SdoTx => 0x580,
+            Function::SdoRx => 0x600,
+            Function::Heartbeat => 0x700,
+        }
+    }
+}
+
+fn check_node(node: u8) -> Result<(), Error> {
+    if node == 0 || node > 127 { return Err(Error::InvalidNodeId(node)) }
+    Ok(())
+}
+
+/// COB-ID of `f` for `node`, node is ignored for NMT & SYNC
+pub fn cob_id(f: Function, node: u8) -> Result<StandardId, Error> {
+    match f {
+        Function::Nmt | Function::Sync => return Ok(StandardId::new(f.base()).unwrap()),
+        Function::Tpdo(n) | Function::Rpdo(n) if n == 0 || n > 4 => return Err(Error::InvalidPdo(n)),
+        _ => check_node(node)?,
+    }
+
+    Ok(StandardId::new(f.base() + node as u16).unwrap())
+}
+
+/// function & node id of a COB-ID, `None` if not in the predefined connection set
+pub fn classify(id: Id) -> Option<(Function, u8)> {
+    let Id::Standard(id) = id else { return None };
+    let raw = id.as_raw();
+
+    match raw {
+        0x000 => return Some((Function::Nmt, 0)),
+        0x080 => return Some((Function::Sync, 0)),
+        _ => (),
+    }
+
+    let node = (raw & 0x7f) as u8;
+    if node == 0 { return None }
+
+    let f = match raw >> 7 {
+        0x1 => Function::Emcy,
+        0x3 | 0x5 | 0x7 | 0x9 => Function::Tpdo(((raw >> 8) as u8) & 0x7),
+        0x4 | 0x6 | 0x8 | 0xa => Function::Rpdo(((raw >> 8) as u8) - 1),
+        0xb => Function::SdoTx,
+        0xc => Function::SdoRx,
+        0xe => Function::Heartbeat,
+        _ => return None,
+    };
+
+    Some((f, node))
+}
+
+// ----------------------------------------- CiA 301 values
+/// value in CiA 301 byte order, i.e. little-endian & byte-aligned,
+/// tuples are objects in mapping order
+pub trait CiaValue: Sized {
+    const SIZE: usize;
+    fn write_le(&self, b: &mut [u8]);
+    fn read_le(b: &[u8]) -> Self;
+}
+
+macro_rules! cia_value_num {
+    ($($t:ty),*) => { $(
+        impl CiaValue for $t {
+            const SIZE: usize = core::mem::size_of::<$t>();
+            fn write_le(&self, b: &mut [u8]) { b[..Self::SIZE].copy_from_slice(&self.to_le_bytes()) }
+            fn read_le(b: &[u8]) -> Self { Self::from_le_bytes(b[..Self::SIZE].try_into().unwrap()) }
+        }
+    )* };
+}
+
+cia_value_num!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+impl CiaValue for bool {
+    const SIZE: usize = 1;
+    fn write_le(&self, b: &mut [u8]) { b[0] = *self as u8 }
+    fn read_le(b: &[u8]) -> Self { b[0] != 0 }
+}
+
+macro_rules! cia_value_tuple {
+    ($($t:ident $i:tt),*) => {
+        impl <$($t: CiaValue),*> CiaValue for ($($t,)*) {
+            const SIZE: usize = 0 $(+ $t::SIZE)*;
+
+            #[allow(unused_assignments)]
+            fn write_le(&self, b: &mut [u8]) {
+                let mut n = 0;
+                $( self.$i.write_le(&mut b[n..]); n += $t::SIZE; )*
+            }
+
+            #[allow(unused_assignments)]
+            fn read_le(b: &[u8]) -> Self {
+                let mut n = 0;
+                ($( { let x = $t::read_le(&b[n..]); n += $t::SIZE; x }, )*)
+            }
+        }
+    };
+}
+
+cia_value_tuple!(A 0, B 1);
+cia_value_tuple!(A 0, B 1, C 2);
+cia_value_tuple!(A 0, B 1, C 2, D 3);
+
+fn cia_bytes<T: CiaValue>(x: &T) -> Result<Vec<u8, 8>, Error> {
+    if T::SIZE > 8 { return Err(Error::FrameErr(crate::frame::Error::MsgTooLong)) }
+    let mut b = [0; 8];
+    x.write_le(&mut b);
+    Ok(Vec::from_slice(&b[..T::SIZE]).unwrap())
+}
+
+fn from_cia_bytes<T: CiaValue>(b: &[u8]) -> Result<T, Error> {
+    if b.len() != T::SIZE { return Err(Error::InvalidPayload) }
+    Ok(T::read_le(b))
+}
+
+// ----------------------------------------- PDO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pdo {
+    Tpdo(u8),
+    Rpdo(u8),
+}
+
+/// message mapped to a PDO
+pub trait PdoMessage {
+    const PDO: Pdo;
+}
+
+fn pdo_function(pdo: Pdo) -> Function {
+    match pdo {
+        Pdo::Tpdo(n) => Function::Tpdo(n),
+        Pdo::Rpdo(n) => Function::Rpdo(n),
+    }
+}
+
+pub fn encode_pdo<F: Frame, T: Serialize + PdoMessage>(node: u8, x: &T) -> Result<F, Error> {
+    let id = cob_id(pdo_function(T::PDO), node)?;
+    Ok(to_frame(id, x)?)
+}
+
+pub fn decode_pdo<'a, T: Deserialize<'a> + PdoMessage, F: Frame>(f: &'a F) -> Result<(u8, T), Error> {
+    match classify(f.id()) {
+        Some((func, node)) if func == pdo_function(T::PDO) => Ok((node, from_frame(f)?)),
+        _ => Err(Error::UnexpectedCobId(raw_id(f.id()))),
+    }
+}
+
+/// PDO of `x` in CiA 301 byte order
+pub fn encode_pdo_cia<F: Frame, T: CiaValue + PdoMessage>(node: u8, x: &T) -> Result<F, Error> {
+    let id = cob_id(pdo_function(T::PDO), node)?;
+    new_frame(id, &cia_bytes(x)?)
+}
+
+pub fn decode_pdo_cia<T: CiaValue + PdoMessage, F: Frame>(f: &F) -> Result<(u8, T), Error> {
+    let node = check_id(f, pdo_function(T::PDO))?;
+    Ok((node, from_cia_bytes(f.data())?))
+}
+
+fn raw_id(id: Id) -> u32 {
+    match id {
+        Id::Standard(x) => x.as_raw() as u32,
+        Id::Extended(x) => x.as_raw(),
+    }
+}
+
+fn new_frame<F: Frame>(id: StandardId, data: &[u8]) -> Result<F, Error> {
+    F::new(id, data).ok_or(Error::FrameErr(crate::frame::Error::MsgTooLong))
+}
+
+fn check_id<F: Frame>(f: &F, func: Function) -> Result<u8, Error> {
+    match classify(f.id()) {
+        Some((x, node)) if x == func && !f.is_remote_frame() => Ok(node),
+        _ => Err(Error::UnexpectedCobId(raw_id(f.id()))),
+    }
+}
+
+// ----------------------------------------- NMT & heartbeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtCommand {
+    Start = 0x01,
+    Stop = 0x02,
+    PreOperational = 0x80,
+    ResetNode = 0x81,
+    ResetCommunication = 0x82,
+}
+
+impl TryFrom<u8> for NmtCommand {
+    type Error = Error;
+
+    fn try_from(x: u8) -> Result<Self, Error> {
+        match x {
+            0x01 => Ok(NmtCommand::Start),
+            0x02 => Ok(NmtCommand::Stop),
+            0x80 => Ok(NmtCommand::PreOperational),
+            0x81 => Ok(NmtCommand::ResetNode),
+            0x82 => Ok(NmtCommand::ResetCommunication),
+            _ => Err(Error::InvalidPayload),
+        }
+    }
+}
+
+/// NMT state reported by heartbeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtState {
+    BootUp = 0x00,
+    Stopped = 0x04,
+    Operational = 0x05,
+    PreOperational = 0x7f,
+}
+
+impl TryFrom<u8> for NmtState {
+    type Error = Error;
+
+    fn try_from(x: u8) -> Result<Self, Error> {
+        // bit 7 is the toggle bit of node guarding
+        match x & 0x7f {
+            0x00 => Ok(NmtState::BootUp),
+            0x04 => Ok(NmtState::Stopped),
+            0x05 => Ok(NmtState::Operational),
+            0x7f => Ok(NmtState::PreOperational),
+            _ => Err(Error::InvalidPayload),
+        }
+    }
+}
+
+/// NMT command to `node`, 0 for all nodes
+pub fn encode_nmt<F: Frame>(cmd: NmtCommand, node: u8) -> Result<F, Error> {
+    if node != 0 { check_node(node)? }
+    let id = cob_id(Function::Nmt, 0)?;
+    new_frame(id, &[cmd as u8, node])
+}
+
+pub fn decode_nmt<F: Frame>(f: &F) -> Result<(NmtCommand, u8), Error> {
+    check_id(f, Function::Nmt)?;
+    let [cmd, node] = f.data() else { return Err(Error::InvalidPayload) };
+    Ok(((*cmd).try_into()?, *node))
+}
+
+pub fn encode_heartbeat<F: Frame>(node: u8, state: NmtState) -> Result<F, Error> {
+    let id = cob_id(Function::Heartbeat, node)?;
+    new_frame(id, &[state as u8])
+}
+
+pub fn decode_heartbeat<F: Frame>(f: &F) -> Result<(u8, NmtState), Error> {
+    let node = check_id(f, Function::Heartbeat)?;
+    let [state] = f.data() else { return Err(Error::InvalidPayload) };
+    Ok((node, (*state).try_into()?))
+}
+
+// ----------------------------------------- SDO
+/// expedited SDO, up to 4 bytes of data
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sdo {
+    /// client request to write
+    Download { index: u16, sub: u8, data: Vec<u8, 4> },
+    /// server confirm of `Download`
+    DownloadAck { index: u16, sub: u8 },
+    /// client request to read
+    Upload { index: u16, sub: u8 },
+    /// server response of `Upload`
+    UploadData { index: u16, sub: u8, data: Vec<u8, 4> },
+    Abort { index: u16, sub: u8, code: u32 },
+}
+
+impl Sdo {
+    /// write serde_can-encoded `x` to object `index:sub`
+    pub fn download<T: Serialize>(index: u16, sub: u8, x: &T) -> Result<Self, Error> {
+        Ok(Sdo::Download { index, sub, data: Self::value_data(x)? })
+    }
+
+    /// response of upload with serde_can-encoded `x`
+    pub fn upload_data<T: Serialize>(index: u16, sub: u8, x: &T) -> Result<Self, Error> {
+        Ok(Sdo::UploadData { index, sub, data: Self::value_data(x)? })
+    }
+
+    /// write `x` in CiA 301 byte order to object `index:sub`
+    pub fn download_cia<T: CiaValue>(index: u16, sub: u8, x: &T) -> Result<Self, Error> {
+        Ok(Sdo::Download { index, sub, data: Self::cia_data(x)? })
+    }
+
+    /// response of upload with `x` in CiA 301 byte order
+    pub fn upload_data_cia<T: CiaValue>(index: u16, sub: u8, x: &T) -> Result<Self, Error> {
+        Ok(Sdo::UploadData { index, sub, data: Self::cia_data(x)? })
+    }
+
+    fn value_data<T: Serialize>(x: &T) -> Result<Vec<u8, 4>, Error> {
+        let b = to_bytes(x).map_err(Error::SerdeErr)?;
+        if b.is_empty() { return Err(Error::SdoValueEmpty) }
+        Vec::from_slice(&b).map_err(|_| Error::SdoValueTooLong(b.len()))
+    }
+
+    fn cia_data<T: CiaValue>(x: &T) -> Result<Vec<u8, 4>, Error> {
+        if T::SIZE > 4 { return Err(Error::SdoValueTooLong(T::SIZE)) }
+        let b = cia_bytes(x)?;
+        Ok(Vec::from_slice(&b).unwrap())
+    }
+
+    /// decode data of `Download` or `UploadData`
+    pub fn value<'a, T: Deserialize<'a>>(&'a self) -> Result<T, Error> {
+        match self {
+            Sdo::Download { data, .. } | Sdo::UploadData { data, .. } => from_bytes(data).map_err(Error::SerdeErr),
+            _ => Err(Error::InvalidPayload),
+        }
+    }
+
+    /// decode data of `Download` or `UploadData` in CiA 301 byte order
+    pub fn value_cia<T: CiaValue>(&self) -> Result<T, Error> {
+        match self {
+            Sdo::Download { data, .. } | Sdo::UploadData { data, .. } => from_cia_bytes(data),
+            _ => Err(Error::InvalidPayload),
+        }
+    }
+
+    pub fn index(&self) -> (u16, u8) {
+        match *self {
+            Sdo::Download { index, sub, .. } | Sdo::DownloadAck { index, sub } | Sdo::Upload { index, sub } |
+            Sdo::UploadData { index, sub, .. } | Sdo::Abort { index, sub, .. } => (index, sub),
+        }
+    }
+
+    // n = 4 does not fit in 2 bits, so no empty data
+    fn expedited(cs: u8, data: &[u8]) -> Result<(u8, [u8; 4]), Error> {
+        if data.is_empty() { return Err(Error::SdoValueEmpty) }
+        let mut d = [0; 4];
+        d[..data.len()].copy_from_slice(data);
+        // expedited, size indicated, n bytes not used
+        Ok((cs | (((4 - data.len()) as u8) << 2) | 0x3, d))
+    }
+
+    pub fn to_payload(&self) -> Result<[u8; 8], Error> {
+        let (index, sub) = self.index();
+        let (cmd, d) = match self {
+            Sdo::Download { data, .. } => Self::expedited(0x20, data)?,
+            Sdo::DownloadAck { .. } => (0x60, [0; 4]),
+            Sdo::Upload { .. } => (0x40, [0; 4]),
+            Sdo::UploadData { data, .. } => Self::expedited(0x40, data)?,
+            Sdo::Abort { code, .. } => (0x80, code.to_le_bytes()),
+        };
+
+        let i = index.to_le_bytes();
+        Ok([cmd, i[0], i[1], sub, d[0], d[1], d[2], d[3]])
+    }
+
+    /// `request` for payload from client, only expedited transfer is supported
+    pub fn from_payload(b: &[u8], request: bool) -> Result<Self, Error> {
+        let [cmd, i0, i1, sub, d0, d1, d2, d3] = *b else { return Err(Error::InvalidPayload) };
+        let index = u16::from_le_bytes([i0, i1]);
+        let d = [d0, d1, d2, d3];
+
+        let data = || {
+            if cmd & 0x2 == 0 { return Err(Error::InvalidPayload) }
+            let len = if cmd & 0x1 == 0 { 4 } else { 4 - ((cmd >> 2) & 0x3) as usize };
+            Ok(Vec::from_slice(&d[..len]).unwrap())
+        };
+
+        match (cmd >> 5, request) {
+            (1, true) => Ok(Sdo::Download { index, sub, data: data()? }),
+            (3, false) => Ok(Sdo::DownloadAck { index, sub }),
+            (2, true) => Ok(Sdo::Upload { index, sub }),
+            (2, false) => Ok(Sdo::UploadData { index, sub, data: data()? }),
+            (4, _) => Ok(Sdo::Abort { index, sub, code: u32::from_le_bytes(d) }),
+            _ => Err(Error::InvalidPayload),
+        }
+    }
+}
+
+/// SDO request from client to `node`
+pub fn encode_sdo_request<F: Frame>(node: u8, sdo: &Sdo) -> Result<F, Error> {
+    let id = cob_id(Function::SdoRx, node)?;
+    new_frame(id, &sdo.to_payload()?)
+}
+
+/// SDO response from server `node`
+pub fn encode_sdo_response<F: Frame>(node: u8, sdo: &Sdo) -> Result<F, Error> {
+    let id = cob_id(Function::SdoTx, node)?;
+    new_frame(id, &sdo.to_payload()?)
+}
+
+/// decode SDO request, return the server node id
+pub fn decode_sdo_request<F: Frame>(f: &F) -> Result<(u8, Sdo), Error> {
+    let node = check_id(f, Function::SdoRx)?;
+    Ok((node, Sdo::from_payload(f.data(), true)?))
+}
+
+/// decode SDO response, return the server node id
+pub fn decode_sdo_response<F: Frame>(f: &F) -> Result<(u8, Sdo), Error> {
+    let node = check_id(f, Function::SdoTx)?;
+    Ok((node, Sdo::from_payload(f.data(), false)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::CanFrame;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Status { state: u8, speed: i16 }
+    impl PdoMessage for Status { const PDO: Pdo = Pdo::Tpdo(1); }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Command { enable: bool, target: i16 }
+    impl PdoMessage for Command { const PDO: Pdo = Pdo::Rpdo(2); }
+
+    fn sid(x: u16) -> Id { Id::Standard(StandardId::new(x).unwrap()) }
+
+    #[test]
+    fn cob() {
+        assert_eq!(cob_id(Function::Tpdo(1), 5), Ok(StandardId::new(0x185).unwrap()));
+        assert_eq!(cob_id(Function::Rpdo(4), 5), Ok(StandardId::new(0x505).unwrap()));
+        assert_eq!(cob_id(Function::Tpdo(5), 5), Err(Error::InvalidPdo(5)));
+        assert_eq!(cob_id(Function::SdoRx, 128), Err(Error::InvalidNodeId(128)));
+
+        for f in [Function::Emcy, Function::SdoTx, Function::SdoRx, Function::Heartbeat] {
+            assert_eq!(classify(cob_id(f, 0x7f).unwrap().into()), Some((f, 0x7f)));
+        }
+        for n in 1..=4 {
+            assert_eq!(classify(sid(0x80 + 0x100 * n + 1)), Some((Function::Tpdo(n as u8), 1)));
+            assert_eq!(classify(sid(0x100 + 0x100 * n + 1)), Some((Function::Rpdo(n as u8), 1)));
+        }
+        assert_eq!(classify(sid(0x000)), Some((Function::Nmt, 0)));
+        assert_eq!(classify(sid(0x080)), Some((Function::Sync, 0)));
+        assert_eq!(classify(sid(0x680)), None);
+        assert_eq!(classify(sid(0x7ff)), None);
+    }
+
+    #[test]
+    fn pdo() {
+        let x = Status { state: 5, speed: -300 };
+        let f: CanFrame = encode_pdo(3, &x).unwrap();
+        assert_eq!(f.id(), sid(0x183));
+        assert_eq!(decode_pdo::<Status, _>(&f), Ok((3, x)));
+        assert_eq!(decode_pdo::<Command, _>(&f), Err(Error::UnexpectedCobId(0x183)));
+
+        let f: CanFrame = encode_pdo(3, &Command { enable: true, target: 100 }).unwrap();
+        assert_eq!(f.id(), sid(0x303));
+    }
+
+    #[test]
+    fn nmt_heartbeat() {
+        let f: CanFrame = encode_nmt(NmtCommand::Start, 0).unwrap();
+        assert_eq!((f.id(), f.data()), (sid(0), &[0x01, 0x00][..]));
+        assert_eq!(decode_nmt(&f), Ok((NmtCommand::Start, 0)));
+
+        let f: CanFrame = encode_heartbeat(0x10, NmtState::Operational).unwrap();
+        assert_eq!((f.id(), f.data()), (sid(0x710), &[0x05][..]));
+        assert_eq!(decode_heartbeat(&f), Ok((0x10, NmtState::Operational)));
+
+        let f = CanFrame::new(StandardId::new(0x710).unwrap(), &[0x85]).unwrap();
+        assert_eq!(decode_heartbeat(&f), Ok((0x10, NmtState::Operational)));
+        let f = CanFrame::new(StandardId::new(0x710).unwrap(), &[0x03]).unwrap();
+        assert_eq!(decode_heartbeat(&f), Err(Error::InvalidPayload));
+    }
+
+    #[test]
+    fn sdo() {
+        // client write 0x1234 to 0x2000:01 of node 5
+        let req = Sdo::download(0x2000, 1, &0x1234u16).unwrap();
+        let f: CanFrame = encode_sdo_request(5, &req).unwrap();
+        assert_eq!(f.id(), sid(0x605));
+        assert_eq!(f.data(), &[0x2b, 0x00, 0x20, 0x01, 0x12, 0x34, 0, 0]);
+
+        let (node, sdo) = decode_sdo_request(&f).unwrap();
+        assert_eq!((node, &sdo), (5, &req));
+        assert_eq!(sdo.value::<u16>(), Ok(0x1234));
+
+        let f: CanFrame = encode_sdo_response(5, &Sdo::DownloadAck { index: 0x2000, sub: 1 }).unwrap();
+        assert_eq!(f.data(), &[0x60, 0x00, 0x20, 0x01, 0, 0, 0, 0]);
+        assert_eq!(decode_sdo_response(&f), Ok((5, Sdo::DownloadAck { index: 0x2000, sub: 1 })));
+
+        // client read 0x1018:01
+        let f: CanFrame = encode_sdo_request(5, &Sdo::Upload { index: 0x1018, sub: 1 }).unwrap();
+        assert_eq!(f.data(), &[0x40, 0x18, 0x10, 0x01, 0, 0, 0, 0]);
+
+        let resp = Sdo::upload_data(0x1018, 1, &(7u8, -1i8, true)).unwrap();
+        let f: CanFrame = encode_sdo_response(5, &resp).unwrap();
+        assert_eq!(f.data()[0], 0x47);
+        let (_, sdo) = decode_sdo_response(&f).unwrap();
+        assert_eq!(sdo.value::<(u8, i8, bool)>(), Ok((7, -1, true)));
+
+        let abort = Sdo::Abort { index: 0x1018, sub: 1, code: 0x0602_0000 };
+        let f: CanFrame = encode_sdo_response(5, &abort).unwrap();
+        assert_eq!(f.data(), &[0x80, 0x18, 0x10, 0x01, 0x00, 0x00, 0x02, 0x06]);
+        assert_eq!(decode_sdo_response(&f), Ok((5, abort)));
+
+        assert_eq!(Sdo::download(0x2000, 0, &0u64), Err(Error::SdoValueTooLong(8)));
+        // empty value, not expressible in expedited transfer
+        assert_eq!(Sdo::download(0x2000, 2, &()), Err(Error::SdoValueEmpty));
+        let req = Sdo::Download { index: 0x2000, sub: 2, data: Vec::new() };
+        assert_eq!(encode_sdo_request::<CanFrame>(5, &req), Err(Error::SdoValueEmpty));
+        // size not indicated, 4 bytes
+        let sdo = Sdo::from_payload(&[0x22, 0x00, 0x20, 0x02, 1, 2, 3, 4], true).unwrap();
+        assert_eq!(sdo.value_cia::<u32>(), Ok(0x0403_0201));
+        // segmented transfer
+        assert_eq!(Sdo::from_payload(&[0x21, 0, 0x20, 0, 10, 0, 0, 0], true), Err(Error::InvalidPayload));
+    }
+
+    #[test]
+    fn cia() {
+        // same object as in `sdo`, in the byte order of a standard device
+        let req = Sdo::download_cia(0x2000, 1, &0x1234u16).unwrap();
+        let f: CanFrame = encode_sdo_request(5, &req).unwrap();
+        assert_eq!(f.data(), &[0x2b, 0x00, 0x20, 0x01, 0x34, 0x12, 0, 0]);
+        let (_, sdo) = decode_sdo_request(&f).unwrap();
+        assert_eq!(sdo.value_cia::<u16>(), Ok(0x1234));
+        assert_eq!(sdo.value_cia::<u32>(), Err(Error::InvalidPayload));
+
+        let resp = Sdo::upload_data_cia(0x1018, 1, &0x1234_5678u32).unwrap();
+        let f: CanFrame = encode_sdo_response(5, &resp).unwrap();
+        assert_eq!(f.data(), &[0x43, 0x18, 0x10, 0x01, 0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(Sdo::download_cia(0x2000, 0, &0u64), Err(Error::SdoValueTooLong(8)));
+
+        #[derive(Debug, PartialEq)]
+        struct Status(u8, i16, bool);
+        impl PdoMessage for Status { const PDO: Pdo = Pdo::Tpdo(1); }
+        impl CiaValue for Status {
+            const SIZE: usize = <(u8, i16, bool)>::SIZE;
+            fn write_le(&self, b: &mut [u8]) { (self.0, self.1, self.2).write_le(b) }
+            fn read_le(b: &[u8]) -> Self { let (a, c, d) = CiaValue::read_le(b); Status(a, c, d) }
+        }
+
+        let f: CanFrame = encode_pdo_cia(3, &Status(5, -300, true)).unwrap();
+        assert_eq!((f.id(), f.data()), (sid(0x183), &[0x05, 0xd4, 0xfe, 0x01][..]));
+        assert_eq!(decode_pdo_cia::<Status, _>(&f), Ok((3, Status(5, -300, true))));
+    }
+}
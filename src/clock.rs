@@ -0,0 +1,10 @@
+//! time source of the sans-IO state machines
+
+/// monotonic time in microseconds
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+impl <F: Fn() -> u64> Clock for F {
+    fn now(&self) -> u64 { self() }
+}
@@ -0,0 +1,197 @@
+//! node liveness by heartbeat msg of a `NodeGroup`
+//!
+//! Each node sends heartbeat msg `H` periodically with its own node_id. A node
+//! is lost if no heartbeat is seen within timeout, and rejoins on the next one.
+
+use core::marker::PhantomData;
+
+use embedded_can::Frame;
+use heapless::Vec;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::node_group::{self, Elem, Group, NodeId};
+use crate::Clock;
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("node table full, node {0} not tracked")]
+    TableFull(NodeId),
+    #[error("node group error: {0}")]
+    NodeGroupErr(node_group::Error),
+}
+
+impl From<node_group::Error> for Error {
+    fn from(e: node_group::Error) -> Self { Error::NodeGroupErr(e) }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// first heartbeat of node
+    Joined(NodeId),
+    Lost(NodeId),
+    /// heartbeat of a lost node
+    Rejoined(NodeId),
+}
+
+#[derive(Debug)]
+struct Node {
+    node_id: NodeId,
+    last: u64,
+    alive: bool,
+}
+
+/// track up to `N` nodes of group `G` by heartbeat msg `H`, and send own heartbeat
+pub struct Monitor<G, H, C, const N: usize = 16> {
+    clock: C,
+    node_id: NodeId,
+    timeout: u64,
+    period: u64,
+    next_tx: u64,
+    nodes: Vec<Node, N>,
+    _phantom: PhantomData<(G, fn(H))>,
+}
+
+impl <G: Group, H: Serialize + DeserializeOwned + Elem<G::Msgs>, C: Clock, const N: usize> Monitor<G, H, C, N> {
+    /// own node `node_id` send heartbeat every `period`, others are lost after `timeout`,
+    /// all in microseconds
+    pub fn new(clock: C, node_id: NodeId, period: u64, timeout: u64) -> Self {
+        let next_tx = clock.now();
+        Monitor { clock, node_id, timeout, period, next_tx, nodes: Vec::new(), _phantom: PhantomData }
+    }
+
+    /// heartbeat of another node, `Ok(None)` for other frames or a refresh of alive node
+    pub fn on_frame<F: Frame>(&mut self, f: &F) -> Result<Option<Event>, Error> {
+        match G::classify(&f.id()) {
+            Some((n, m)) if n != self.node_id && !f.is_remote_frame() && m == G::msg_id::<H>() => {},
+            _ => return Ok(None),
+        }
+
+        let (node_id, _) = G::decode::<H, F>(f)?;
+        let now = self.clock.now();
+
+        if let Some(x) = self.nodes.iter_mut().find(|x| x.node_id == node_id) {
+            x.last = now;
+            if x.alive { return Ok(None) }
+            x.alive = true;
+            return Ok(Some(Event::Rejoined(node_id)));
+        }
+
+        self.nodes.push(Node { node_id, last: now, alive: true }).map_err(|_| Error::TableFull(node_id))?;
+        Ok(Some(Event::Joined(node_id)))
+    }
+
+    /// one node lost since last call
+    pub fn poll(&mut self) -> Option<Event> {
+        let now = self.clock.now();
+        let x = self.nodes.iter_mut().find(|x| x.alive && now >= x.last + self.timeout)?;
+        x.alive = false;
+        Some(Event::Lost(x.node_id))
+    }
+
+    /// own heartbeat frame when it is due
+    pub fn heartbeat<F: Frame>(&mut self, x: &H) -> Result<Option<F>, Error> {
+        let now = self.clock.now();
+        if now < self.next_tx { return Ok(None) }
+
+        let f = G::encode_ext(self.node_id, x)?;
+        // keep the pace, unless a whole period is missed
+        self.next_tx = if now >= self.next_tx + self.period { now + self.period } else { self.next_tx + self.period };
+        Ok(Some(f))
+    }
+
+    /// time of next heartbeat or timeout
+    pub fn poll_at(&self) -> u64 {
+        self.nodes.iter().filter(|x| x.alive).map(|x| x.last + self.timeout).fold(self.next_tx, u64::min)
+    }
+
+    pub fn is_alive(&self, node_id: NodeId) -> bool {
+        self.nodes.iter().any(|x| x.node_id == node_id && x.alive)
+    }
+
+    /// time of last heartbeat of node, `None` if never seen
+    pub fn last_seen(&self, node_id: NodeId) -> Option<u64> {
+        self.nodes.iter().find(|x| x.node_id == node_id).map(|x| x.last)
+    }
+
+    pub fn alive(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.iter().filter(|x| x.alive).map(|x| x.node_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+    use core::cell::Cell;
+    use serde::Deserialize;
+
+    use crate::CanFrame;
+    use crate::node_group::{Cons, Nil, NodeGroup};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum State { Boot, Run }
+
+    crate::node_group_msg_def!(T, [u8, State]);
+    type G = NodeGroup<T, 0x1_9876_500, 3, 3>;
+
+    fn hb(node_id: NodeId) -> CanFrame {
+        G::encode_ext(node_id, &State::Run).unwrap()
+    }
+
+    #[test]
+    fn liveness() {
+        let t = Cell::new(0);
+        let mut m = Monitor::<G, State, _, 2>::new(|| t.get(), 1, 100, 250);
+
+        assert_eq!(m.on_frame(&hb(2)), Ok(Some(Event::Joined(2))));
+        // own heartbeat & other msgs
+        assert_eq!(m.on_frame(&hb(1)), Ok(None));
+        assert_eq!(m.on_frame(&G::encode_ext::<CanFrame, _>(3, &0u8).unwrap()), Ok(None));
+
+        t.set(100);
+        assert_eq!(m.on_frame(&hb(3)), Ok(Some(Event::Joined(3))));
+        assert_eq!(m.on_frame(&hb(4)), Err(Error::TableFull(4)));
+
+        t.set(200);
+        assert_eq!(m.on_frame(&hb(2)), Ok(None));
+        assert_eq!(m.poll(), None);
+        assert_eq!(m.alive().collect::<std::vec::Vec<_>>(), [2, 3]);
+
+        t.set(350);
+        assert_eq!(m.poll(), Some(Event::Lost(3)));
+        assert_eq!(m.poll(), None);
+        assert!(m.is_alive(2) && !m.is_alive(3));
+        assert_eq!(m.last_seen(3), Some(100));
+
+        t.set(400);
+        assert_eq!(m.on_frame(&hb(3)), Ok(Some(Event::Rejoined(3))));
+        assert_eq!(m.last_seen(5), None);
+    }
+
+    #[test]
+    fn heartbeat() {
+        let t = Cell::new(1000);
+        let mut m = Monitor::<G, State, _>::new(|| t.get(), 1, 100, 250);
+
+        let f: CanFrame = m.heartbeat(&State::Boot).unwrap().unwrap();
+        assert_eq!(G::decode::<State, _>(&f), Ok((1, State::Boot)));
+        assert_eq!(m.heartbeat::<CanFrame>(&State::Run), Ok(None));
+        assert_eq!(m.poll_at(), 1100);
+
+        t.set(1120);
+        assert!(m.heartbeat::<CanFrame>(&State::Run).unwrap().is_some());
+        assert_eq!(m.poll_at(), 1200);
+
+        // late, restart from now
+        t.set(1500);
+        assert!(m.heartbeat::<CanFrame>(&State::Run).unwrap().is_some());
+        assert_eq!(m.poll_at(), 1600);
+
+        m.on_frame(&hb(2)).unwrap();
+        t.set(1700);
+        assert!(m.heartbeat::<CanFrame>(&State::Run).unwrap().is_some());
+        assert_eq!(m.poll_at(), 1750);
+
+        assert!(Monitor::<G, State, _>::new(|| 0, 8, 100, 250).heartbeat::<CanFrame>(&State::Run).is_err());
+    }
+}
@@ -0,0 +1,485 @@
+//! ISO-TP (ISO 15765-2) segmentation, for payload up to 4095 bytes
+//!
+//! Payload is raw bytes, not a single serde_can value, which is at most 8
+//! bytes; e.g. a sequence of values from `to_bytes`, or a byte blob.
+//!
+//! Sans-IO state machines, work on data of classic CAN frames with normal
+//! addressing. Time is read from the injected `Clock`, in microseconds.
+//!
+//! ```text
+//! SF  | 0 len |  data...
+//! FF  | 1 len(12 bits) |  data...
+//! CF  | 2 seq |  data...
+//! FC  | 3 flag | block size | STmin |
+//! ```
+
+use heapless::Vec;
+
+use crate::Clock;
+
+/// data of one CAN frame
+pub type FrameData = Vec<u8, 8>;
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("payload length {0} too large")]
+    TooLong(usize),
+    #[error("empty payload")]
+    Empty,
+    #[error("transfer in progress")]
+    Busy,
+    #[error("timeout")]
+    Timeout,
+    #[error("receiver overflow")]
+    Overflow,
+    #[error("sequence number mismatch, expect {0}, got {1}")]
+    SeqMismatch(u8, u8),
+    #[error("invalid frame")]
+    InvalidFrame,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    /// number of consecutive frames before next flow control, 0 for no limit
+    pub block_size: u8,
+    /// STmin sent in flow control, raw value
+    pub st_min: u8,
+    /// pad frames to 8 bytes
+    pub padding: Option<u8>,
+    /// N_Bs & N_Cr timeout
+    pub timeout: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { block_size: 0, st_min: 0, padding: None, timeout: 1_000_000 }
+    }
+}
+
+const SF: u8 = 0;
+const FF: u8 = 1;
+const CF: u8 = 2;
+const FC: u8 = 3;
+
+const FC_CTS: u8 = 0;
+const FC_WAIT: u8 = 1;
+const FC_OVERFLOW: u8 = 2;
+
+/// 0x00 - 0x7f ms, 0xf1 - 0xf9 100 - 900 us, reserved values as 127 ms
+fn st_min_us(raw: u8) -> u64 {
+    match raw {
+        0x00..=0x7f => raw as u64 * 1000,
+        0xf1..=0xf9 => (raw - 0xf0) as u64 * 100,
+        _ => 127_000,
+    }
+}
+
+fn frame(cfg: &Config, pci: &[u8], data: &[u8]) -> FrameData {
+    let mut f = FrameData::new();
+    // pci + data never longer than 8 bytes
+    let _ = f.extend_from_slice(pci);
+    let _ = f.extend_from_slice(data);
+
+    if let Some(p) = cfg.padding {
+        while f.push(p).is_ok() {}
+    }
+
+    f
+}
+
+// ----------------------------------------- sender
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TxState {
+    Idle,
+    Single,
+    First,
+    WaitFc { deadline: u64 },
+    Consecutive { next: u64, block_remain: Option<u8>, st_min: u64 },
+}
+
+#[derive(Debug)]
+pub struct Sender<C, const N: usize = 4095> {
+    clock: C,
+    cfg: Config,
+    buf: Vec<u8, N>,
+    offset: usize,
+    seq: u8,
+    state: TxState,
+}
+
+impl <C: Clock, const N: usize> Sender<C, N> {
+    pub fn new(cfg: Config, clock: C) -> Self {
+        Sender { clock, cfg, buf: Vec::new(), offset: 0, seq: 0, state: TxState::Idle }
+    }
+
+    pub fn is_idle(&self) -> bool { self.state == TxState::Idle }
+
+    /// start transfer of `data`, frames are got from `poll`
+    pub fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+        if !self.is_idle() { return Err(Error::Busy) }
+        if data.len() > 4095 { return Err(Error::TooLong(data.len())) }
+        // SF_DL 0 is invalid
+        if data.is_empty() { return Err(Error::Empty) }
+
+        self.buf.clear();
+        self.buf.extend_from_slice(data).map_err(|_| Error::TooLong(data.len()))?;
+        self.state = if data.len() <= 7 { TxState::Single } else { TxState::First };
+
+        Ok(())
+    }
+
+    /// next frame to transmit, `Err(Timeout)` if no flow control in time
+    pub fn poll(&mut self) -> Result<Option<FrameData>, Error> {
+        let now = self.clock.now();
+
+        match self.state {
+            TxState::Idle => Ok(None),
+
+            TxState::Single => {
+                self.state = TxState::Idle;
+                Ok(Some(frame(&self.cfg, &[(SF << 4) | self.buf.len() as u8], &self.buf)))
+            },
+
+            TxState::First => {
+                let len = self.buf.len();
+                self.offset = 6;
+                self.seq = 1;
+                self.state = TxState::WaitFc { deadline: now + self.cfg.timeout };
+                Ok(Some(frame(&self.cfg, &[(FF << 4) | (len >> 8) as u8, len as u8], &self.buf[..6])))
+            },
+
+            TxState::WaitFc { deadline } => {
+                if now < deadline { return Ok(None) }
+                self.state = TxState::Idle;
+                Err(Error::Timeout)
+            },
+
+            TxState::Consecutive { next, block_remain, st_min } => {
+                if now < next { return Ok(None) }
+
+                let end = (self.offset + 7).min(self.buf.len());
+                let f = frame(&self.cfg, &[(CF << 4) | self.seq], &self.buf[self.offset..end]);
+                self.offset = end;
+                self.seq = (self.seq + 1) & 0xf;
+
+                self.state = match block_remain {
+                    _ if end == self.buf.len() => TxState::Idle,
+                    Some(1) => TxState::WaitFc { deadline: now + self.cfg.timeout },
+                    b => TxState::Consecutive { next: now + st_min, block_remain: b.map(|x| x - 1), st_min },
+                };
+
+                Ok(Some(f))
+            },
+        }
+    }
+
+    /// time `poll` should be called next, `None` if waiting for nothing
+    pub fn poll_at(&self) -> Option<u64> {
+        match self.state {
+            TxState::Idle => None,
+            TxState::Single | TxState::First => Some(0),
+            TxState::WaitFc { deadline } => Some(deadline),
+            TxState::Consecutive { next, .. } => Some(next),
+        }
+    }
+
+    /// handle flow control frame from receiver, other frames are ignored
+    pub fn on_frame(&mut self, data: &[u8]) -> Result<(), Error> {
+        let TxState::WaitFc { .. } = self.state else { return Ok(()) };
+        if data.first().map(|x| x >> 4) != Some(FC) { return Ok(()) }
+        if data.len() < 3 { return Err(Error::InvalidFrame) }
+        let now = self.clock.now();
+
+        match data[0] & 0xf {
+            FC_CTS => {
+                let block_remain = if data[1] == 0 { None } else { Some(data[1]) };
+                self.state = TxState::Consecutive { next: now, block_remain, st_min: st_min_us(data[2]) };
+                Ok(())
+            },
+            FC_WAIT => {
+                self.state = TxState::WaitFc { deadline: now + self.cfg.timeout };
+                Ok(())
+            },
+            FC_OVERFLOW => {
+                self.state = TxState::Idle;
+                Err(Error::Overflow)
+            },
+            _ => {
+                self.state = TxState::Idle;
+                Err(Error::InvalidFrame)
+            },
+        }
+    }
+}
+
+// ----------------------------------------- receiver
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RxState {
+    Idle,
+    Receiving { len: usize, deadline: u64, block_remain: u8 },
+}
+
+#[derive(Debug)]
+pub struct Receiver<C, const N: usize = 4095> {
+    clock: C,
+    cfg: Config,
+    buf: Vec<u8, N>,
+    seq: u8,
+    state: RxState,
+    fc: Option<FrameData>,
+}
+
+impl <C: Clock, const N: usize> Receiver<C, N> {
+    pub fn new(cfg: Config, clock: C) -> Self {
+        Receiver { clock, cfg, buf: Vec::new(), seq: 0, state: RxState::Idle, fc: None }
+    }
+
+    fn flow_control(&mut self, flag: u8) {
+        self.fc = Some(frame(&self.cfg, &[(FC << 4) | flag, self.cfg.block_size, self.cfg.st_min], &[]));
+    }
+
+    /// handle frame from sender, return the payload when complete,
+    /// flow control frames are ignored
+    pub fn on_frame(&mut self, data: &[u8]) -> Result<Option<&[u8]>, Error> {
+        let Some(pci) = data.first() else { return Err(Error::InvalidFrame) };
+        let now = self.clock.now();
+
+        match pci >> 4 {
+            SF => {
+                let len = (pci & 0xf) as usize;
+                if len == 0 || len > 7 || len + 1 > data.len() { return Err(Error::InvalidFrame) }
+
+                // new transfer abort the one in progress
+                self.state = RxState::Idle;
+                self.buf.clear();
+                self.buf.extend_from_slice(&data[1..=len]).map_err(|_| Error::TooLong(len))?;
+                Ok(Some(&self.buf))
+            },
+
+            FF => {
+                if data.len() < 8 { return Err(Error::InvalidFrame) }
+                let len = (((pci & 0xf) as usize) << 8) | data[1] as usize;
+                if len < 8 { return Err(Error::InvalidFrame) }
+
+                self.state = RxState::Idle;
+                if len > N {
+                    self.flow_control(FC_OVERFLOW);
+                    return Err(Error::TooLong(len));
+                }
+
+                self.buf.clear();
+                let _ = self.buf.extend_from_slice(&data[2..8]);
+                self.seq = 1;
+                self.state = RxState::Receiving { len, deadline: now + self.cfg.timeout, block_remain: self.cfg.block_size };
+                self.flow_control(FC_CTS);
+                Ok(None)
+            },
+
+            CF => {
+                let RxState::Receiving { len, block_remain, .. } = self.state else { return Ok(None) };
+
+                let seq = pci & 0xf;
+                if seq != self.seq {
+                    self.state = RxState::Idle;
+                    return Err(Error::SeqMismatch(self.seq, seq));
+                }
+
+                let n = (len - self.buf.len()).min(7);
+                if data.len() < n + 1 {
+                    self.state = RxState::Idle;
+                    return Err(Error::InvalidFrame);
+                }
+                let _ = self.buf.extend_from_slice(&data[1..=n]);
+                self.seq = (self.seq + 1) & 0xf;
+
+                if self.buf.len() == len {
+                    self.state = RxState::Idle;
+                    return Ok(Some(&self.buf));
+                }
+
+                let block_remain = match block_remain {
+                    0 => 0,
+                    1 => { self.flow_control(FC_CTS); self.cfg.block_size },
+                    b => b - 1,
+                };
+                self.state = RxState::Receiving { len, deadline: now + self.cfg.timeout, block_remain };
+                Ok(None)
+            },
+
+            FC => Ok(None),
+            _ => Err(Error::InvalidFrame),
+        }
+    }
+
+    /// flow control frame to transmit, `Err(Timeout)` if no consecutive frame in time
+    pub fn poll(&mut self) -> Result<Option<FrameData>, Error> {
+        if let Some(f) = self.fc.take() { return Ok(Some(f)) }
+
+        match self.state {
+            RxState::Receiving { deadline, .. } if self.clock.now() >= deadline => {
+                self.state = RxState::Idle;
+                Err(Error::Timeout)
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+// ----------------------------------------- channel
+/// sender & receiver of one side, frames of both direction use the same handling
+#[derive(Debug)]
+pub struct Channel<C, const N: usize = 4095> {
+    pub tx: Sender<C, N>,
+    pub rx: Receiver<C, N>,
+}
+
+impl <C: Clock + Clone, const N: usize> Channel<C, N> {
+    pub fn new(cfg: Config, clock: C) -> Self {
+        Channel { tx: Sender::new(cfg, clock.clone()), rx: Receiver::new(cfg, clock) }
+    }
+
+    pub fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.tx.send(data)
+    }
+
+    /// next frame to transmit
+    pub fn poll(&mut self) -> Result<Option<FrameData>, Error> {
+        match self.rx.poll()? {
+            Some(f) => Ok(Some(f)),
+            None => self.tx.poll(),
+        }
+    }
+
+    /// handle received frame, return payload when complete
+    pub fn on_frame(&mut self, data: &[u8]) -> Result<Option<&[u8]>, Error> {
+        if data.first().map(|x| x >> 4) == Some(FC) {
+            self.tx.on_frame(data)?;
+            return Ok(None);
+        }
+
+        self.rx.on_frame(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+    use core::cell::Cell;
+    use std::vec::Vec;
+
+    use crate::test_util::{clock, payload};
+
+    // run until quiet, return payloads received by `b` & time of last frame of `a`
+    fn run<C: Clock + Clone>(a: &mut Channel<C>, b: &mut Channel<C>, t: &Cell<u64>) -> Result<(Vec<Vec<u8>>, u64), Error> {
+        let mut res = Vec::new();
+        let mut last = t.get();
+
+        crate::test_util::run(t, 100, 2_000_000, || {
+            let mut sent = false;
+            if let Some(f) = a.poll()? {
+                (sent, last) = (true, t.get());
+                if let Some(p) = b.on_frame(&f)? { res.push(p.to_vec()); }
+            }
+            if let Some(f) = b.poll()? {
+                sent = true;
+                a.on_frame(&f)?;
+            }
+            Ok(sent)
+        })?;
+
+        Ok((res, last))
+    }
+
+    #[test]
+    fn single() {
+        let t = Cell::new(0);
+        let mut a = Channel::new(Config::default(), clock(&t));
+        let mut b = Channel::new(Config::default(), clock(&t));
+
+        let x = crate::to_bytes(&(1u8, -2i16)).unwrap();
+        a.send(&x).unwrap();
+        let (r, _) = run(&mut a, &mut b, &t).unwrap();
+        assert_eq!(crate::from_bytes::<(u8, i16)>(&r[0]), Ok((1, -2)));
+    }
+
+    #[test]
+    fn multi() {
+        for len in [8, 13, 14, 100, 4095] {
+            let t = Cell::new(0);
+            let mut a = Channel::new(Config::default(), clock(&t));
+            let mut b = Channel::new(Config::default(), clock(&t));
+
+            a.send(&payload(len)).unwrap();
+            let (r, _) = run(&mut a, &mut b, &t).unwrap();
+            assert_eq!(r, [payload(len)]);
+            assert!(a.tx.is_idle());
+        }
+    }
+
+    #[test]
+    fn block_size_st_min() {
+        let t = Cell::new(0);
+        let cfg = Config { block_size: 2, st_min: 5, padding: Some(0xcc), ..Default::default() };
+        let mut a = Channel::new(Config::default(), clock(&t));
+        let mut b = Channel::new(cfg, clock(&t));
+
+        // FF + 5 CF in blocks of 2, CF in a block 5ms apart
+        a.send(&payload(40)).unwrap();
+        let (r, last) = run(&mut a, &mut b, &t).unwrap();
+        assert_eq!(r, [payload(40)]);
+        assert!(last >= 2 * 5000);
+    }
+
+    #[test]
+    fn padding() {
+        let mut a = Sender::<_, 4095>::new(Config { padding: Some(0xcc), ..Default::default() }, || 0);
+        a.send(&[1, 2]).unwrap();
+        assert_eq!(a.poll().unwrap().unwrap(), [0x02, 1, 2, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc]);
+    }
+
+    #[test]
+    fn err() {
+        let t = Cell::new(0);
+        let mut a = Channel::<_, 4095>::new(Config::default(), clock(&t));
+        assert_eq!(a.send(&[0; 4096]), Err(Error::TooLong(4096)));
+        assert_eq!(a.send(&[]), Err(Error::Empty));
+        assert!(a.tx.is_idle());
+
+        a.send(&[0; 100]).unwrap();
+        assert_eq!(a.send(&[0; 1]), Err(Error::Busy));
+
+        // no flow control
+        assert!(a.poll().unwrap().is_some());
+        t.set(999_999);
+        assert_eq!(a.poll(), Ok(None));
+        t.set(1_000_000);
+        assert_eq!(a.poll(), Err(Error::Timeout));
+
+        // receiver too small
+        t.set(0);
+        let mut a = Channel::<_, 4095>::new(Config::default(), clock(&t));
+        let mut b = Channel::<_, 64>::new(Config::default(), clock(&t));
+        a.send(&[0; 100]).unwrap();
+        let f = a.poll().unwrap().unwrap();
+        assert_eq!(b.on_frame(&f), Err(Error::TooLong(100)));
+        let f = b.poll().unwrap().unwrap();
+        assert_eq!(a.on_frame(&f), Err(Error::Overflow));
+
+        // lost consecutive frame
+        let mut a = Channel::<_, 4095>::new(Config::default(), clock(&t));
+        let mut b = Channel::<_, 4095>::new(Config::default(), clock(&t));
+        a.send(&[0; 100]).unwrap();
+        b.on_frame(&a.poll().unwrap().unwrap()).unwrap();
+        a.on_frame(&b.poll().unwrap().unwrap()).unwrap();
+        let _ = a.poll().unwrap().unwrap();
+        assert_eq!(b.on_frame(&a.poll().unwrap().unwrap()), Err(Error::SeqMismatch(1, 2)));
+
+        // receive timeout
+        let mut b = Channel::<_, 4095>::new(Config::default(), clock(&t));
+        b.on_frame(&[0x10, 20, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert!(b.poll().unwrap().is_some());
+        t.set(1_000_000);
+        assert_eq!(b.poll(), Err(Error::Timeout));
+    }
+}
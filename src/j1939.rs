@@ -0,0 +1,216 @@
+//! J1939 29-bit identifier & typed messages by PGN
+//!
+//! ```text
+//! | priority | EDP | DP |  PF  |  PS  |  SA  |
+//!     3        1     1     8      8      8
+//! ```
+//!
+//! PF < 240 is PDU1, PS is the destination address and is not part of the PGN;
+//! otherwise PDU2, PS is the group extension.
+
+use embedded_can::{ExtendedId, Frame, Id};
+use serde::{Deserialize, Serialize};
+
+use crate::{from_frame, to_frame};
+
+pub mod tp;
+
+pub type Pgn = u32;
+
+/// global destination address
+pub const GLOBAL: u8 = 0xff;
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("invalid pgn {0:#x}")]
+    InvalidPgn(Pgn),
+    #[error("priority {0} > 7")]
+    InvalidPriority(u8),
+    #[error("not an extended id")]
+    NotExtended,
+    #[error("pgn mismatch, {0:#x} != {1:#x}")]
+    PgnMismatch(Pgn, Pgn),
+    #[error("payload of {0} bytes, a serde_can value is at most 8 bytes")]
+    PayloadTooLong(usize),
+    #[error("frame error: {0}")]
+    FrameErr(crate::frame::Error),
+    #[error("serde error: {0}")]
+    SerdeErr(crate::Error),
+}
+
+impl From<crate::frame::Error> for Error {
+    fn from(e: crate::frame::Error) -> Self {
+        match e {
+            crate::frame::Error::SerdeErr(e) => Error::SerdeErr(e),
+            _ => Error::FrameErr(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct J1939Id {
+    pub priority: u8,
+    pub pgn: Pgn,
+    /// destination address, always `GLOBAL` for PDU2
+    pub dst: u8,
+    pub src: u8,
+}
+
+/// PDU1 if PF < 240
+pub const fn is_pdu1(pgn: Pgn) -> bool {
+    (pgn >> 8) & 0xff < 240
+}
+
+impl J1939Id {
+    /// `dst` is ignored for PDU2 pgn
+    pub fn new(priority: u8, pgn: Pgn, dst: u8, src: u8) -> Result<Self, Error> {
+        if priority > 7 { return Err(Error::InvalidPriority(priority)) }
+        if pgn >= (1 << 18) || (is_pdu1(pgn) && pgn & 0xff != 0) {
+            return Err(Error::InvalidPgn(pgn));
+        }
+
+        let dst = if is_pdu1(pgn) { dst } else { GLOBAL };
+        Ok(J1939Id { priority, pgn, dst, src })
+    }
+
+    pub fn from_raw(raw: u32) -> Self {
+        let priority = ((raw >> 26) & 0x7) as u8;
+        let pgn = (raw >> 8) & 0x3_ffff;
+        let src = raw as u8;
+
+        if is_pdu1(pgn) {
+            J1939Id { priority, pgn: pgn & !0xff, dst: pgn as u8, src }
+        } else {
+            J1939Id { priority, pgn, dst: GLOBAL, src }
+        }
+    }
+
+    /// fields are masked to their width, as they are public & may be out of range
+    pub fn as_raw(&self) -> u32 {
+        let pgn = self.pgn & 0x3_ffff;
+        let pdu = if is_pdu1(pgn) { (pgn & !0xff) | self.dst as u32 } else { pgn };
+        ((self.priority as u32 & 0x7) << 26) | (pdu << 8) | self.src as u32
+    }
+
+    pub fn is_pdu1(&self) -> bool { is_pdu1(self.pgn) }
+
+    /// extended data page
+    pub fn edp(&self) -> bool { self.pgn & (1 << 17) != 0 }
+
+    /// data page
+    pub fn dp(&self) -> bool { self.pgn & (1 << 16) != 0 }
+
+    /// PDU format
+    pub fn pf(&self) -> u8 { (self.pgn >> 8) as u8 }
+
+    /// PDU specific, destination address or group extension
+    pub fn ps(&self) -> u8 {
+        if self.is_pdu1() { self.dst } else { self.pgn as u8 }
+    }
+}
+
+impl From<ExtendedId> for J1939Id {
+    fn from(id: ExtendedId) -> Self { Self::from_raw(id.as_raw()) }
+}
+
+impl From<J1939Id> for ExtendedId {
+    fn from(id: J1939Id) -> Self {
+        // 3 + 18 + 8 bits
+        ExtendedId::new(id.as_raw()).unwrap()
+    }
+}
+
+impl TryFrom<Id> for J1939Id {
+    type Error = Error;
+
+    fn try_from(id: Id) -> Result<Self, Error> {
+        match id {
+            Id::Extended(x) => Ok(x.into()),
+            Id::Standard(_) => Err(Error::NotExtended),
+        }
+    }
+}
+
+/// message with a fixed PGN
+pub trait Message {
+    const PGN: Pgn;
+    const PRIORITY: u8 = 6;
+}
+
+/// `dst` is ignored for PDU2 messages
+pub fn encode<F: Frame, T: Serialize + Message>(src: u8, dst: u8, x: &T) -> Result<F, Error> {
+    let id = J1939Id::new(T::PRIORITY, T::PGN, dst, src)?;
+    Ok(to_frame(ExtendedId::from(id), x)?)
+}
+
+pub fn decode<'a, T: Deserialize<'a> + Message, F: Frame>(f: &'a F) -> Result<(J1939Id, T), Error> {
+    let id = J1939Id::try_from(f.id())?;
+    if id.pgn != T::PGN { return Err(Error::PgnMismatch(id.pgn, T::PGN)) }
+
+    Ok((id, from_frame(f)?))
+}
+
+/// decode reassembled payload of `pgn`, e.g. of TP or fast-packet, error if
+/// longer than a serde_can value instead of dropping the rest
+pub(crate) fn decode_payload<'a, T: Deserialize<'a> + Message>(pgn: Pgn, data: &'a [u8]) -> Result<T, Error> {
+    if pgn != T::PGN { return Err(Error::PgnMismatch(pgn, T::PGN)) }
+    if data.len() > 8 { return Err(Error::PayloadTooLong(data.len())) }
+    crate::from_bytes(data).map_err(Error::SerdeErr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::CanFrame;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Eec1 { torque_mode: u8, driver_demand: u8, actual: u8, speed: u16 }
+    impl Message for Eec1 { const PGN: Pgn = 0xf004; const PRIORITY: u8 = 3; }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Request(u8, u8, u8);
+    impl Message for Request { const PGN: Pgn = 0xea00; }
+
+    #[test]
+    fn id() {
+        // EEC1 from engine
+        let id = J1939Id::from_raw(0x0cf0_0400);
+        assert_eq!(id, J1939Id { priority: 3, pgn: 0xf004, dst: GLOBAL, src: 0 });
+        assert!(!id.is_pdu1());
+        assert_eq!((id.pf(), id.ps()), (0xf0, 0x04));
+
+        // request from 0xf9 to 0x00
+        let id = J1939Id::from_raw(0x18ea_00f9);
+        assert_eq!(id, J1939Id { priority: 6, pgn: 0xea00, dst: 0, src: 0xf9 });
+        assert!(id.is_pdu1() && !id.dp() && !id.edp());
+        assert_eq!(id.as_raw(), 0x18ea_00f9);
+
+        let id = J1939Id::new(6, 0x3_fe00, 0x20, 0x10).unwrap();
+        assert!(id.dp() && id.edp());
+        assert_eq!(id.dst, GLOBAL);
+
+        assert_eq!(J1939Id::new(8, 0xf004, 0, 0), Err(Error::InvalidPriority(8)));
+        assert_eq!(J1939Id::new(6, 0xea01, 0, 0), Err(Error::InvalidPgn(0xea01)));
+        assert_eq!(J1939Id::new(6, 0x4_0000, 0, 0), Err(Error::InvalidPgn(0x4_0000)));
+
+        // out of range fields
+        let id = J1939Id { priority: 0xff, pgn: 0xfc_ea12, dst: 0x20, src: 0x10 };
+        assert_eq!(ExtendedId::from(id).as_raw(), 0x1cea_2010);
+    }
+
+    #[test]
+    fn endec() {
+        let x = Eec1 { torque_mode: 1, driver_demand: 125, actual: 130, speed: 0x1a40 };
+        let f: CanFrame = encode(0x00, 0x20, &x).unwrap();
+        assert_eq!(f.id(), Id::Extended(ExtendedId::new(0x0cf0_0400).unwrap()));
+        assert_eq!(decode::<Eec1, _>(&f), Ok((J1939Id::from_raw(0x0cf0_0400), x)));
+        assert_eq!(decode::<Request, _>(&f), Err(Error::PgnMismatch(0xf004, 0xea00)));
+
+        let f: CanFrame = encode(0xf9, 0x00, &Request(0xe5, 0xfe, 0x00)).unwrap();
+        let (id, _) = decode::<Request, _>(&f).unwrap();
+        assert_eq!((id.dst, id.src), (0x00, 0xf9));
+
+        let f = CanFrame::new(embedded_can::StandardId::ZERO, &[]).unwrap();
+        assert_eq!(decode::<Request, _>(&f), Err(Error::NotExtended));
+    }
+}
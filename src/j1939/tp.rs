@@ -0,0 +1,639 @@
+//! J1939 transport protocol, TP.CM & TP.DT, for payload of 9 to 1785 bytes
+//!
+//! Sans-IO state machines, packets are `(J1939Id, [u8; 8])`, time is read
+//! from the injected `Clock`, in microseconds. Destination `GLOBAL` is sent
+//! by BAM, others by RTS/CTS. One session per direction at a time.
+
+use heapless::{Deque, Vec};
+use serde::Deserialize;
+
+use super::{J1939Id, Message, Pgn, GLOBAL};
+use crate::Clock;
+
+pub const TP_CM: Pgn = 0xec00;
+pub const TP_DT: Pgn = 0xeb00;
+
+/// 255 packets of 7 bytes
+pub const MAX_LEN: usize = 1785;
+
+pub type Packet = (J1939Id, [u8; 8]);
+
+const RTS: u8 = 16;
+const CTS: u8 = 17;
+const EOMA: u8 = 19;
+const BAM: u8 = 32;
+const ABORT: u8 = 255;
+
+const PRIORITY: u8 = 7;
+
+// timeouts of J1939-21
+const T1: u64 = 750_000;
+const T2: u64 = 1_250_000;
+const T3: u64 = 1_250_000;
+const T4: u64 = 1_050_000;
+/// gap between BAM data packets
+const BAM_GAP: u64 = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    AlreadyInSession,
+    Resources,
+    Timeout,
+    CtsWhileSending,
+    MaxRetransmit,
+    UnexpectedData,
+    BadSequence,
+    DuplicateSequence,
+    TooLarge,
+    /// 250, not in the list of J1939-21, e.g. CTS of invalid sequence number
+    Unspecified,
+    Other(u8),
+}
+
+impl From<u8> for AbortReason {
+    fn from(x: u8) -> Self {
+        use AbortReason::*;
+        match x {
+            1 => AlreadyInSession,
+            2 => Resources,
+            3 => Timeout,
+            4 => CtsWhileSending,
+            5 => MaxRetransmit,
+            6 => UnexpectedData,
+            7 => BadSequence,
+            8 => DuplicateSequence,
+            9 => TooLarge,
+            250 => Unspecified,
+            x => Other(x),
+        }
+    }
+}
+
+impl From<AbortReason> for u8 {
+    fn from(x: AbortReason) -> Self {
+        use AbortReason::*;
+        match x {
+            AlreadyInSession => 1,
+            Resources => 2,
+            Timeout => 3,
+            CtsWhileSending => 4,
+            MaxRetransmit => 5,
+            UnexpectedData => 6,
+            BadSequence => 7,
+            DuplicateSequence => 8,
+            TooLarge => 9,
+            Unspecified => 250,
+            Other(x) => x,
+        }
+    }
+}
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("payload length {0} not in 9..=1785 or too large for buffer")]
+    InvalidLength(usize),
+    #[error("session in progress")]
+    Busy,
+    #[error("timeout")]
+    Timeout,
+    #[error("aborted, {0:?}")]
+    Aborted(AbortReason),
+    #[error("sequence number mismatch, expect {0}, got {1}")]
+    SeqMismatch(u8, u8),
+}
+
+fn packets(len: usize) -> u8 { len.div_ceil(7) as u8 }
+
+fn tp_id(pgn: Pgn, dst: u8, src: u8) -> J1939Id {
+    J1939Id { priority: PRIORITY, pgn, dst, src }
+}
+
+fn cm(ctrl: u8, b: [u8; 4], pgn: Pgn) -> [u8; 8] {
+    [ctrl, b[0], b[1], b[2], b[3], pgn as u8, (pgn >> 8) as u8, (pgn >> 16) as u8]
+}
+
+fn cm_pgn(data: &[u8; 8]) -> Pgn {
+    data[5] as Pgn | (data[6] as Pgn) << 8 | (data[7] as Pgn) << 16
+}
+
+fn abort(reason: AbortReason, pgn: Pgn, dst: u8, src: u8) -> Packet {
+    (tp_id(TP_CM, dst, src), cm(ABORT, [reason.into(), 0xff, 0xff, 0xff], pgn))
+}
+
+// ----------------------------------------- sender
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TxState {
+    Idle,
+    Announce,
+    Bam { seq: u8, next: u64 },
+    WaitCts { deadline: u64 },
+    Data { seq: u8, end: u8 },
+    WaitEoma { deadline: u64 },
+    Abort(AbortReason),
+}
+
+#[derive(Debug)]
+pub struct Sender<C, const N: usize = MAX_LEN> {
+    clock: C,
+    addr: u8,
+    pgn: Pgn,
+    dst: u8,
+    buf: Vec<u8, N>,
+    state: TxState,
+}
+
+impl <C: Clock, const N: usize> Sender<C, N> {
+    /// `addr` is source address of this node
+    pub fn new(addr: u8, clock: C) -> Self {
+        Sender { clock, addr, pgn: 0, dst: GLOBAL, buf: Vec::new(), state: TxState::Idle }
+    }
+
+    pub fn is_idle(&self) -> bool { self.state == TxState::Idle }
+
+    /// start session of `data` to `dst`, packets are got from `poll`
+    pub fn send(&mut self, pgn: Pgn, dst: u8, data: &[u8]) -> Result<(), Error> {
+        if !self.is_idle() { return Err(Error::Busy) }
+        if data.len() < 9 || data.len() > MAX_LEN { return Err(Error::InvalidLength(data.len())) }
+
+        self.buf.clear();
+        self.buf.extend_from_slice(data).map_err(|_| Error::InvalidLength(data.len()))?;
+        self.pgn = pgn;
+        self.dst = dst;
+        self.state = TxState::Announce;
+
+        Ok(())
+    }
+
+    fn total(&self) -> u8 { packets(self.buf.len()) }
+
+    fn data(&self, seq: u8) -> Packet {
+        let mut d = [0xff; 8];
+        d[0] = seq;
+
+        let start = (seq as usize - 1) * 7;
+        let end = (start + 7).min(self.buf.len());
+        d[1..=end - start].copy_from_slice(&self.buf[start..end]);
+
+        (tp_id(TP_DT, self.dst, self.addr), d)
+    }
+
+    /// next packet to transmit, on timeout return `Err(Timeout)` and send abort on next poll
+    pub fn poll(&mut self) -> Result<Option<Packet>, Error> {
+        let len = self.buf.len();
+        let now = self.clock.now();
+
+        match self.state {
+            TxState::Idle => Ok(None),
+
+            TxState::Announce => {
+                let b = [len as u8, (len >> 8) as u8, self.total(), 0xff];
+                let ctrl = if self.dst == GLOBAL {
+                    self.state = TxState::Bam { seq: 1, next: now + BAM_GAP };
+                    BAM
+                } else {
+                    self.state = TxState::WaitCts { deadline: now + T3 };
+                    RTS
+                };
+
+                Ok(Some((tp_id(TP_CM, self.dst, self.addr), cm(ctrl, b, self.pgn))))
+            },
+
+            TxState::Bam { seq, next } => {
+                if now < next { return Ok(None) }
+
+                self.state = if seq == self.total() {
+                    TxState::Idle
+                } else {
+                    TxState::Bam { seq: seq + 1, next: now + BAM_GAP }
+                };
+                Ok(Some(self.data(seq)))
+            },
+
+            TxState::WaitCts { deadline } | TxState::WaitEoma { deadline } => {
+                if now < deadline { return Ok(None) }
+                self.state = TxState::Abort(AbortReason::Timeout);
+                Err(Error::Timeout)
+            },
+
+            TxState::Data { seq, end } => {
+                self.state = match seq {
+                    _ if seq == self.total() => TxState::WaitEoma { deadline: now + T3 },
+                    _ if seq == end => TxState::WaitCts { deadline: now + T3 },
+                    _ => TxState::Data { seq: seq + 1, end },
+                };
+                Ok(Some(self.data(seq)))
+            },
+
+            TxState::Abort(reason) => {
+                self.state = TxState::Idle;
+                Ok(Some(abort(reason, self.pgn, self.dst, self.addr)))
+            },
+        }
+    }
+
+    /// time `poll` should be called next, `None` if waiting for nothing
+    pub fn poll_at(&self) -> Option<u64> {
+        match self.state {
+            TxState::Idle => None,
+            TxState::Bam { next, .. } => Some(next),
+            TxState::WaitCts { deadline } | TxState::WaitEoma { deadline } => Some(deadline),
+            TxState::Announce | TxState::Data { .. } | TxState::Abort(_) => Some(0),
+        }
+    }
+
+    /// handle CTS, EoMA & abort of current session, other packets are ignored
+    pub fn on_packet(&mut self, id: J1939Id, data: &[u8; 8]) -> Result<(), Error> {
+        if id.pgn != TP_CM || id.dst != self.addr || id.src != self.dst || cm_pgn(data) != self.pgn {
+            return Ok(());
+        }
+        let now = self.clock.now();
+
+        match (data[0], self.state) {
+            (CTS, TxState::WaitCts { .. }) => {
+                let (n, seq) = (data[1], data[2]);
+                self.state = if n == 0 {
+                    TxState::WaitCts { deadline: now + T4 }
+                } else if seq == 0 || seq > self.total() {
+                    TxState::Abort(AbortReason::Unspecified)
+                } else {
+                    TxState::Data { seq, end: seq.saturating_add(n - 1).min(self.total()) }
+                };
+                Ok(())
+            },
+            (CTS, TxState::Data { .. }) => {
+                self.state = TxState::Abort(AbortReason::CtsWhileSending);
+                Err(Error::Aborted(AbortReason::CtsWhileSending))
+            },
+            (EOMA, TxState::WaitEoma { .. }) => {
+                self.state = TxState::Idle;
+                Ok(())
+            },
+            (ABORT, TxState::WaitCts { .. } | TxState::Data { .. } | TxState::WaitEoma { .. }) => {
+                self.state = TxState::Idle;
+                Err(Error::Aborted(data[1].into()))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+// ----------------------------------------- receiver
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RxState {
+    Idle,
+    Bam { src: u8, pgn: Pgn, len: usize, seq: u8, deadline: u64 },
+    Cmdt { src: u8, pgn: Pgn, len: usize, seq: u8, end: u8, window: u8, deadline: u64 },
+}
+
+/// reassembled payload
+#[derive(Debug, PartialEq)]
+pub struct Transfer<'a> {
+    pub pgn: Pgn,
+    pub src: u8,
+    pub dst: u8,
+    pub data: &'a [u8],
+}
+
+impl <'a> Transfer<'a> {
+    /// decode payload with `from_bytes`, error if it is over the 8 bytes of a serde_can value
+    pub fn decode<T: Message + Deserialize<'a>>(&self) -> Result<T, super::Error> {
+        super::decode_payload(self.pgn, self.data)
+    }
+}
+
+#[derive(Debug)]
+pub struct Receiver<C, const N: usize = MAX_LEN> {
+    clock: C,
+    addr: u8,
+    max_packets: u8,
+    buf: Vec<u8, N>,
+    state: RxState,
+    out: Deque<Packet, 4>,
+}
+
+impl <C: Clock, const N: usize> Receiver<C, N> {
+    /// `addr` is address of this node
+    pub fn new(addr: u8, clock: C) -> Self {
+        Receiver { clock, addr, max_packets: 0xff, buf: Vec::new(), state: RxState::Idle, out: Deque::new() }
+    }
+
+    /// max number of packets per CTS
+    pub fn with_max_packets(mut self, n: u8) -> Self {
+        self.max_packets = n.max(1);
+        self
+    }
+
+    fn queue(&mut self, p: Packet) {
+        // at most 2 packets of one session & 1 abort are pending
+        let _ = self.out.push_back(p);
+    }
+
+    fn cts(&mut self, src: u8, pgn: Pgn, len: usize, seq: u8, window: u8) -> u8 {
+        let n = window.min(packets(len) - seq + 1);
+        self.queue((tp_id(TP_CM, src, self.addr), cm(CTS, [n, seq, 0xff, 0xff], pgn)));
+        seq + (n - 1)
+    }
+
+    /// handle TP packet, return the payload when complete
+    pub fn on_packet(&mut self, id: J1939Id, data: &[u8; 8]) -> Result<Option<Transfer<'_>>, Error> {
+        if id.dst != self.addr && id.dst != GLOBAL { return Ok(None) }
+        let now = self.clock.now();
+
+        match id.pgn {
+            TP_CM => self.on_cm(id, data, now),
+            TP_DT => self.on_dt(id, data, now),
+            _ => Ok(None),
+        }
+    }
+
+    fn on_cm(&mut self, id: J1939Id, data: &[u8; 8], now: u64) -> Result<Option<Transfer<'_>>, Error> {
+        let pgn = cm_pgn(data);
+        let len = data[1] as usize | (data[2] as usize) << 8;
+
+        match data[0] {
+            BAM if id.dst == GLOBAL => {
+                if let RxState::Cmdt { .. } = self.state { return Ok(None) }
+                if len > N || !(9..=MAX_LEN).contains(&len) { return Err(Error::InvalidLength(len)) }
+
+                self.buf.clear();
+                self.state = RxState::Bam { src: id.src, pgn, len, seq: 1, deadline: now + T1 };
+                Ok(None)
+            },
+
+            RTS if id.dst == self.addr => {
+                if self.state != RxState::Idle {
+                    self.queue(abort(AbortReason::AlreadyInSession, pgn, id.src, self.addr));
+                    return Ok(None);
+                }
+                if len > N || !(9..=MAX_LEN).contains(&len) {
+                    self.queue(abort(AbortReason::Resources, pgn, id.src, self.addr));
+                    return Err(Error::InvalidLength(len));
+                }
+
+                self.buf.clear();
+                // max packets per CTS, 0 is invalid & taken as no limit like 0xff
+                let window = match data[4] { 0 => self.max_packets, n => self.max_packets.min(n) };
+                let end = self.cts(id.src, pgn, len, 1, window);
+                self.state = RxState::Cmdt { src: id.src, pgn, len, seq: 1, end, window, deadline: now + T2 };
+                Ok(None)
+            },
+
+            ABORT if id.dst == self.addr => match self.state {
+                RxState::Cmdt { src, pgn: p, .. } if src == id.src && p == pgn => {
+                    self.state = RxState::Idle;
+                    Err(Error::Aborted(data[1].into()))
+                },
+                _ => Ok(None),
+            },
+
+            _ => Ok(None),
+        }
+    }
+
+    fn on_dt(&mut self, id: J1939Id, data: &[u8; 8], now: u64) -> Result<Option<Transfer<'_>>, Error> {
+        let (src, pgn, len, seq) = match self.state {
+            RxState::Bam { src, pgn, len, seq, .. } if src == id.src && id.dst == GLOBAL => (src, pgn, len, seq),
+            RxState::Cmdt { src, pgn, len, seq, .. } if src == id.src && id.dst == self.addr => (src, pgn, len, seq),
+            _ => return Ok(None),
+        };
+
+        if data[0] != seq {
+            if let RxState::Cmdt { .. } = self.state {
+                let reason = if data[0] < seq { AbortReason::DuplicateSequence } else { AbortReason::BadSequence };
+                self.queue(abort(reason, pgn, src, self.addr));
+            }
+            self.state = RxState::Idle;
+            return Err(Error::SeqMismatch(seq, data[0]));
+        }
+
+        let n = (len - self.buf.len()).min(7);
+        let _ = self.buf.extend_from_slice(&data[1..=n]);
+
+        if self.buf.len() == len {
+            let dst = if let RxState::Cmdt { .. } = self.state {
+                let b = [len as u8, (len >> 8) as u8, packets(len), 0xff];
+                self.queue((tp_id(TP_CM, src, self.addr), cm(EOMA, b, pgn)));
+                self.addr
+            } else {
+                GLOBAL
+            };
+
+            self.state = RxState::Idle;
+            return Ok(Some(Transfer { pgn, src, dst, data: &self.buf }));
+        }
+
+        self.state = match self.state {
+            RxState::Bam { .. } => RxState::Bam { src, pgn, len, seq: seq + 1, deadline: now + T1 },
+            RxState::Cmdt { end, window, .. } if seq == end => {
+                let end = self.cts(src, pgn, len, seq + 1, window);
+                RxState::Cmdt { src, pgn, len, seq: seq + 1, end, window, deadline: now + T2 }
+            },
+            RxState::Cmdt { end, window, .. } => RxState::Cmdt { src, pgn, len, seq: seq + 1, end, window, deadline: now + T1 },
+            RxState::Idle => RxState::Idle,
+        };
+        Ok(None)
+    }
+
+    /// next packet to transmit, `Err(Timeout)` if no data packet in time
+    pub fn poll(&mut self) -> Result<Option<Packet>, Error> {
+        if let Some(p) = self.out.pop_front() { return Ok(Some(p)) }
+        let now = self.clock.now();
+
+        match self.state {
+            RxState::Bam { deadline, .. } if now >= deadline => {
+                self.state = RxState::Idle;
+                Err(Error::Timeout)
+            },
+            RxState::Cmdt { src, pgn, deadline, .. } if now >= deadline => {
+                self.state = RxState::Idle;
+                self.queue(abort(AbortReason::Timeout, pgn, src, self.addr));
+                Err(Error::Timeout)
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+// ----------------------------------------- channel
+/// sender & receiver of one node
+#[derive(Debug)]
+pub struct Channel<C, const N: usize = MAX_LEN> {
+    pub tx: Sender<C, N>,
+    pub rx: Receiver<C, N>,
+}
+
+impl <C: Clock + Clone, const N: usize> Channel<C, N> {
+    pub fn new(addr: u8, clock: C) -> Self {
+        Channel { tx: Sender::new(addr, clock.clone()), rx: Receiver::new(addr, clock) }
+    }
+
+    pub fn send(&mut self, pgn: Pgn, dst: u8, data: &[u8]) -> Result<(), Error> {
+        self.tx.send(pgn, dst, data)
+    }
+
+    /// next packet to transmit
+    pub fn poll(&mut self) -> Result<Option<Packet>, Error> {
+        match self.rx.poll()? {
+            Some(p) => Ok(Some(p)),
+            None => self.tx.poll(),
+        }
+    }
+
+    /// handle received packet, return payload when complete
+    pub fn on_packet(&mut self, id: J1939Id, data: &[u8; 8]) -> Result<Option<Transfer<'_>>, Error> {
+        self.tx.on_packet(id, data)?;
+        self.rx.on_packet(id, data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+    use core::cell::Cell;
+    use std::vec::Vec;
+
+    use crate::test_util::{clock, payload};
+
+    type Received = Vec<(usize, Pgn, u8, Vec<u8>)>;
+
+    // every packet goes to all other nodes, run until no packet for 2s
+    fn run<C: Clock + Clone>(nodes: &mut [Channel<C>], t: &Cell<u64>) -> Result<Received, Error> {
+        let mut res = Vec::new();
+
+        crate::test_util::run(t, 1000, 2_000_000, || {
+            let mut sent = false;
+            for i in 0..nodes.len() {
+                let Some((id, data)) = nodes[i].poll()? else { continue };
+                sent = true;
+
+                for (j, n) in nodes.iter_mut().enumerate() {
+                    if i == j { continue }
+                    if let Some(t) = n.on_packet(id, &data)? {
+                        res.push((j, t.pgn, t.src, t.data.to_vec()));
+                    }
+                }
+            }
+            Ok(sent)
+        })?;
+
+        Ok(res)
+    }
+
+    #[test]
+    fn abort_reason() {
+        for x in 0..=255u8 {
+            assert_eq!(u8::from(AbortReason::from(x)), x);
+        }
+    }
+
+    #[test]
+    fn bam() {
+        let t = Cell::new(0);
+        let mut nodes = [Channel::new(0x10, clock(&t)), Channel::new(0x20, clock(&t)), Channel::new(0x30, clock(&t))];
+        nodes[0].send(0xfeca, GLOBAL, &payload(20)).unwrap();
+
+        let r = run(&mut nodes, &t).unwrap();
+        assert_eq!(r, [(1, 0xfeca, 0x10, payload(20)), (2, 0xfeca, 0x10, payload(20))]);
+    }
+
+    #[test]
+    fn cmdt() {
+        for len in [9, 14, 15, 100, MAX_LEN] {
+            let t = Cell::new(0);
+            let mut nodes = [Channel::new(0x10, clock(&t)), Channel::new(0x20, clock(&t)), Channel::new(0x30, clock(&t))];
+            nodes[1].rx = Receiver::new(0x20, clock(&t)).with_max_packets(3);
+            nodes[0].send(0xef00, 0x20, &payload(len)).unwrap();
+
+            let r = run(&mut nodes, &t).unwrap();
+            assert_eq!(r, [(1, 0xef00, 0x10, payload(len))]);
+            assert!(nodes[0].tx.is_idle());
+        }
+    }
+
+    #[test]
+    fn decode() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Dm1 { lamp: u8, flash: u8, spn: u16 }
+        impl Message for Dm1 { const PGN: Pgn = 0xfeca; }
+
+        let data = [0x04, 0xff, 0x12, 0x34, 0, 0, 0, 0, 0, 0];
+        let t = Transfer { pgn: 0xfeca, src: 0, dst: GLOBAL, data: &data[..8] };
+        assert_eq!(t.decode::<Dm1>(), Ok(Dm1 { lamp: 4, flash: 0xff, spn: 0x1234 }));
+
+        // rest of payload is not dropped
+        let t = Transfer { data: &data, ..t };
+        assert_eq!(t.decode::<Dm1>(), Err(crate::j1939::Error::PayloadTooLong(10)));
+
+        let t = Transfer { pgn: 0xfecb, ..t };
+        assert_eq!(t.decode::<Dm1>(), Err(crate::j1939::Error::PgnMismatch(0xfecb, 0xfeca)));
+    }
+
+    #[test]
+    fn err() {
+        let t = Cell::new(0);
+        let mut a = Channel::<_, MAX_LEN>::new(0x10, clock(&t));
+        assert_eq!(a.send(0xef00, 0x20, &[0; 8]), Err(Error::InvalidLength(8)));
+        assert_eq!(a.send(0xef00, 0x20, &[0; 1786]), Err(Error::InvalidLength(1786)));
+
+        // no CTS, abort after timeout
+        a.send(0xef00, 0x20, &[0; 20]).unwrap();
+        assert_eq!(a.send(0xef00, 0x20, &[0; 20]), Err(Error::Busy));
+        let (_, d) = a.poll().unwrap().unwrap();
+        assert_eq!(d[0], RTS);
+        t.set(T3 - 1);
+        assert_eq!(a.poll(), Ok(None));
+        t.set(T3);
+        assert_eq!(a.poll(), Err(Error::Timeout));
+        let (id, d) = a.poll().unwrap().unwrap();
+        assert_eq!((id.dst, d[0], AbortReason::from(d[1])), (0x20, ABORT, AbortReason::Timeout));
+
+        // receiver too small
+        t.set(0);
+        let mut a = Channel::<_, MAX_LEN>::new(0x10, clock(&t));
+        let mut b = Channel::<_, 16>::new(0x20, clock(&t));
+        a.send(0xef00, 0x20, &[0; 20]).unwrap();
+        let (id, d) = a.poll().unwrap().unwrap();
+        assert_eq!(b.on_packet(id, &d), Err(Error::InvalidLength(20)));
+        let (id, d) = b.poll().unwrap().unwrap();
+        assert_eq!(a.on_packet(id, &d), Err(Error::Aborted(AbortReason::Resources)));
+        assert!(a.tx.is_idle());
+
+        // lost data packet
+        let mut a = Channel::<_, MAX_LEN>::new(0x10, clock(&t));
+        let mut b = Channel::<_, MAX_LEN>::new(0x20, clock(&t));
+        a.send(0xef00, 0x20, &[0; 20]).unwrap();
+        let (id, d) = a.poll().unwrap().unwrap();
+        b.on_packet(id, &d).unwrap();
+        let (id, d) = b.poll().unwrap().unwrap();
+        a.on_packet(id, &d).unwrap();
+        let _ = a.poll().unwrap().unwrap();
+        let (id, d) = a.poll().unwrap().unwrap();
+        assert_eq!(b.on_packet(id, &d), Err(Error::SeqMismatch(1, 2)));
+        let (id, d) = b.poll().unwrap().unwrap();
+        assert_eq!(a.on_packet(id, &d), Err(Error::Aborted(AbortReason::BadSequence)));
+
+        // RTS of no max packets per CTS
+        let mut b = Channel::<_, MAX_LEN>::new(0x20, clock(&t));
+        b.on_packet(tp_id(TP_CM, 0x20, 0x10), &cm(RTS, [20, 0, 3, 0], 0xef00)).unwrap();
+        let (_, d) = b.poll().unwrap().unwrap();
+        assert_eq!(&d[..3], [CTS, 3, 1]);
+
+        // CTS of seq 0
+        let mut a = Channel::<_, MAX_LEN>::new(0x10, clock(&t));
+        a.send(0xef00, 0x20, &[0; 20]).unwrap();
+        let _ = a.poll().unwrap().unwrap();
+        a.on_packet(tp_id(TP_CM, 0x10, 0x20), &cm(CTS, [3, 0, 0xff, 0xff], 0xef00)).unwrap();
+        let (_, d) = a.poll().unwrap().unwrap();
+        assert_eq!((d[0], AbortReason::from(d[1])), (ABORT, AbortReason::Unspecified));
+
+        // BAM timeout
+        let mut a = Channel::<_, MAX_LEN>::new(0x10, clock(&t));
+        let mut b = Channel::<_, MAX_LEN>::new(0x20, clock(&t));
+        a.send(0xfeca, GLOBAL, &[0; 20]).unwrap();
+        let (id, d) = a.poll().unwrap().unwrap();
+        b.on_packet(id, &d).unwrap();
+        t.set(T1);
+        assert_eq!(b.poll(), Err(Error::Timeout));
+    }
+}
@@ -1,18 +1,60 @@
 #![no_std]
+#[cfg(feature = "std")]
+extern crate std;
+
 mod de;
 mod error;
 mod ser;
 
 #[cfg(test)]
 mod test;
+#[cfg(test)]
+mod test_util;
+
+pub mod clock;
+pub use clock::Clock;
+
+pub mod isotp;
+
+pub mod max_size;
+pub use max_size::MaxSize;
 
 #[cfg(feature = "embedded-can")]
 mod frame;
 #[cfg(feature = "embedded-can")]
 pub use frame::{from_frame, to_frame};
 
+#[cfg(feature = "embedded-can")]
+pub mod can_frame;
+#[cfg(feature = "embedded-can")]
+pub use can_frame::CanFrame;
+
+#[cfg(feature = "embedded-can")]
+pub mod transport;
+
+#[cfg(feature = "embedded-can")]
+pub mod j1939;
+
+#[cfg(feature = "embedded-can")]
+pub mod canopen;
+
+#[cfg(feature = "embedded-can")]
+pub mod nmea2000;
+
+#[cfg(all(feature = "std", feature = "embedded-can"))]
+pub mod candump;
+#[cfg(all(feature = "std", feature = "embedded-can"))]
+pub mod asc;
+
 #[cfg(feature = "node-group")]
 pub mod node_group;
+#[cfg(feature = "node-group")]
+pub mod rpc;
+#[cfg(feature = "node-group")]
+pub mod heartbeat;
+
+#[cfg(all(feature = "socketcan", target_os = "linux"))]
+pub mod socketcan;
 
 pub use de::{from_bytes, Deserializer};
 pub use error::{Error, Result};
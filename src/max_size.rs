@@ -0,0 +1,111 @@
+//! upper bound of encoded size of a type
+//!
+//! Implemented for primitives, `Option`, arrays & tuples. For struct & enum,
+//! implement it with `struct_max_bits` & `enum_max_bits`, e.g.
+//!
+//! ```
+//! use serde_can::max_size::{MaxSize, struct_max_bits, enum_max_bits};
+//!
+//! struct Status { on: bool, speed: u16 }
+//! impl MaxSize for Status {
+//!     const MAX_BITS: usize = struct_max_bits(&[bool::MAX_BITS, u16::MAX_BITS]);
+//! }
+//!
+//! enum Cmd { Stop, Go(i8) }
+//! impl MaxSize for Cmd {
+//!     const MAX_BITS: usize = enum_max_bits(&[0, i8::MAX_BITS]);
+//! }
+//!
+//! assert_eq!((Status::MAX_SIZE, Cmd::MAX_SIZE), (3, 2));
+//! ```
+
+use core::marker::PhantomData;
+
+pub trait MaxSize {
+    const MAX_BITS: usize;
+    /// max length in bytes
+    const MAX_SIZE: usize = Self::MAX_BITS.div_ceil(8);
+}
+
+/// fields left to right
+pub const fn struct_max_bits(fields: &[usize]) -> usize {
+    let mut res = 0;
+    let mut i = 0;
+    while i < fields.len() {
+        res += fields[i];
+        i += 1;
+    }
+    res
+}
+
+/// 4 bits variant index, then the largest variant
+pub const fn enum_max_bits(variants: &[usize]) -> usize {
+    let mut res = 0;
+    let mut i = 0;
+    while i < variants.len() {
+        if variants[i] > res { res = variants[i] }
+        i += 1;
+    }
+    4 + res
+}
+
+macro_rules! impl_max_size {
+    ( $( $t: ty = $n: expr ),* $(,)? ) => {
+        $( impl MaxSize for $t { const MAX_BITS: usize = $n; } )*
+    };
+}
+
+impl_max_size!(
+    () = 0, bool = 1,
+    u8 = 8, u16 = 16, u32 = 32, u64 = 64,
+    i8 = 8, i16 = 16, i32 = 32, i64 = 64,
+    f32 = 32, f64 = 64,
+    // serde writes them as u64 & i64 on any target
+    usize = 64, isize = 64,
+    // 4 bits length, up to 4 bytes utf-8
+    char = 36,
+);
+
+impl <T: ?Sized> MaxSize for PhantomData<T> {
+    const MAX_BITS: usize = 0;
+}
+
+impl <T: MaxSize> MaxSize for Option<T> {
+    const MAX_BITS: usize = 1 + T::MAX_BITS;
+}
+
+impl <T: MaxSize, const N: usize> MaxSize for [T; N] {
+    const MAX_BITS: usize = N * T::MAX_BITS;
+}
+
+macro_rules! impl_max_size_tuple {
+    ( $( ($( $t: ident ),+) ),* ) => {
+        $( impl <$( $t: MaxSize ),+> MaxSize for ($( $t, )+) {
+            const MAX_BITS: usize = 0 $( + $t::MAX_BITS )+;
+        } )*
+    };
+}
+
+impl_max_size_tuple!(
+    (A), (A, B), (A, B, C), (A, B, C, D), (A, B, C, D, E),
+    (A, B, C, D, E, F), (A, B, C, D, E, F, G), (A, B, C, D, E, F, G, H)
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::to_bytes;
+
+    #[test]
+    fn max_size() {
+        assert_eq!(to_bytes(&'\u{10ffff}').unwrap().len(), char::MAX_SIZE);
+        assert_eq!(to_bytes(&Some((true, 1u16, -1i32))).unwrap().len(), <Option<(bool, u16, i32)>>::MAX_SIZE);
+        assert_eq!(to_bytes(&[1u8; 7]).unwrap().len(), <[u8; 7]>::MAX_SIZE);
+        assert_eq!(<(bool, Option<u8>)>::MAX_BITS, 10);
+        assert_eq!(<(u64, bool)>::MAX_SIZE, 9);
+        assert_eq!(to_bytes(&usize::MAX).unwrap().len(), usize::MAX_SIZE);
+        assert_eq!(to_bytes(&-1isize).unwrap().len(), isize::MAX_SIZE);
+        assert_eq!(struct_max_bits(&[]), 0);
+        assert_eq!(enum_max_bits(&[8, 0, 16]), 20);
+    }
+}
@@ -0,0 +1,275 @@
+//! NMEA 2000 fast-packet, for payload up to 223 bytes
+//!
+//! ```text
+//! first frame | seq(3) | 0(5) | len | 6 bytes data |
+//! others      | seq(3) | frame(5) | 7 bytes data |
+//! ```
+//!
+//! Identifiers are J1939, see `j1939::J1939Id`. Which PGNs are fast-packet is
+//! defined by the application, only feed those frames to `Reassembler`, which
+//! reads time from the injected `Clock`, in microseconds.
+
+use heapless::Vec;
+use serde::Deserialize;
+
+use crate::j1939::{self, J1939Id, Message, Pgn};
+use crate::Clock;
+
+/// 6 + 31 * 7 bytes
+pub const MAX_LEN: usize = 223;
+
+/// time since last frame before a partial message is dropped
+const TIMEOUT: u64 = 750_000;
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("payload length {0} > 223")]
+    TooLong(usize),
+    #[error("frame counter mismatch, expect {0}, got {1}")]
+    SeqMismatch(u8, u8),
+    #[error("invalid frame")]
+    InvalidFrame,
+}
+
+/// frames of one fast-packet message
+pub struct Segments<'a> {
+    seq: u8,
+    data: &'a [u8],
+    frame: u8,
+    offset: usize,
+}
+
+impl Iterator for Segments<'_> {
+    type Item = [u8; 8];
+
+    fn next(&mut self) -> Option<[u8; 8]> {
+        if self.offset >= self.data.len() && self.frame > 0 { return None }
+
+        let mut f = [0xff; 8];
+        f[0] = (self.seq << 5) | self.frame;
+
+        let start = if self.frame == 0 { 2 } else { 1 };
+        if self.frame == 0 { f[1] = self.data.len() as u8 }
+
+        let end = (self.offset + 8 - start).min(self.data.len());
+        f[start..start + end - self.offset].copy_from_slice(&self.data[self.offset..end]);
+
+        self.offset = end;
+        self.frame += 1;
+        Some(f)
+    }
+}
+
+/// split `data` into frames, `seq` is the 3 bits sequence counter,
+/// increased for each message of the same PGN
+pub fn segment(seq: u8, data: &[u8]) -> Result<Segments<'_>, Error> {
+    if data.len() > MAX_LEN { return Err(Error::TooLong(data.len())) }
+    Ok(Segments { seq: seq & 0x7, data, frame: 0, offset: 0 })
+}
+
+/// reassembled payload
+#[derive(Debug, PartialEq)]
+pub struct FastPacket<'a> {
+    pub pgn: Pgn,
+    pub src: u8,
+    pub dst: u8,
+    pub data: &'a [u8],
+}
+
+impl <'a> FastPacket<'a> {
+    /// decode payload with `from_bytes`, same checks as `tp::Transfer::decode`
+    pub fn decode<T: Message + Deserialize<'a>>(&self) -> Result<T, j1939::Error> {
+        j1939::decode_payload(self.pgn, self.data)
+    }
+}
+
+#[derive(Debug)]
+struct Slot {
+    id: J1939Id,
+    seq: u8,
+    frame: u8,
+    len: usize,
+    last: u64,
+    buf: Vec<u8, MAX_LEN>,
+}
+
+/// reassemble fast-packets of up to `S` interleaved source/PGN pairs
+#[derive(Debug)]
+pub struct Reassembler<C, const S: usize = 8> {
+    clock: C,
+    slots: Vec<Slot, S>,
+    done: Option<Slot>,
+}
+
+impl <C: Clock, const S: usize> Reassembler<C, S> {
+    pub fn new(clock: C) -> Self {
+        Reassembler { clock, slots: Vec::new(), done: None }
+    }
+
+    fn find(&self, id: &J1939Id) -> Option<usize> {
+        self.slots.iter().position(|x| x.id.pgn == id.pgn && x.id.src == id.src && x.id.dst == id.dst)
+    }
+
+    /// handle frame, return the payload when complete
+    pub fn on_frame(&mut self, id: J1939Id, data: &[u8]) -> Result<Option<FastPacket<'_>>, Error> {
+        if data.len() < 2 { return Err(Error::InvalidFrame) }
+        let (seq, frame) = (data[0] >> 5, data[0] & 0x1f);
+        let now = self.clock.now();
+
+        self.slots.retain(|x| now.saturating_sub(x.last) < TIMEOUT);
+        let idx = self.find(&id);
+
+        if frame == 0 {
+            let len = data[1] as usize;
+            if len > MAX_LEN { return Err(Error::TooLong(len)) }
+
+            // new message drop the partial one
+            if let Some(i) = idx { self.slots.swap_remove(i); }
+
+            let mut slot = Slot { id, seq, frame: 1, len, last: now, buf: Vec::new() };
+            let n = (data.len() - 2).min(len);
+            let _ = slot.buf.extend_from_slice(&data[2..2 + n]);
+            return Ok(self.push(slot));
+        }
+
+        // first frame missed
+        let Some(i) = idx else { return Ok(None) };
+        let slot = &mut self.slots[i];
+
+        if seq != slot.seq || frame != slot.frame {
+            let expect = slot.frame;
+            self.slots.swap_remove(i);
+            return Err(Error::SeqMismatch(expect, frame));
+        }
+
+        let n = (slot.len - slot.buf.len()).min(data.len() - 1);
+        let _ = slot.buf.extend_from_slice(&data[1..1 + n]);
+        slot.frame += 1;
+        slot.last = now;
+
+        let slot = self.slots.swap_remove(i);
+        Ok(self.push(slot))
+    }
+
+    fn push(&mut self, slot: Slot) -> Option<FastPacket<'_>> {
+        if slot.buf.len() == slot.len {
+            let done = self.done.insert(slot);
+            return Some(FastPacket { pgn: done.id.pgn, src: done.id.src, dst: done.id.dst, data: &done.buf });
+        }
+
+        if self.slots.is_full() {
+            // drop the stalest one
+            let (i, _) = self.slots.iter().enumerate().min_by_key(|(_, x)| x.last).unwrap();
+            self.slots.swap_remove(i);
+        }
+        let _ = self.slots.push(slot);
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+    use core::cell::Cell;
+    use std::vec::Vec;
+
+    use crate::test_util::{clock, payload};
+
+    fn id(pgn: Pgn, src: u8) -> J1939Id {
+        J1939Id::new(3, pgn, 0xff, src).unwrap()
+    }
+
+    // all frames of `data` in a row, return pgn, src & data of the payload completed
+    fn feed<C: Clock>(r: &mut Reassembler<C>, id: J1939Id, seq: u8, data: &[u8]) -> Option<(Pgn, u8, Vec<u8>)> {
+        let mut res = None;
+        for f in segment(seq, data).unwrap() {
+            if let Some(p) = r.on_frame(id, &f).unwrap() {
+                res = Some((p.pgn, p.src, p.data.to_vec()));
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn segment_frames() {
+        let f: Vec<_> = segment(2, &payload(13)).unwrap().collect();
+        assert_eq!(f, [
+            [0x40, 13, 0, 1, 2, 3, 4, 5],
+            [0x41, 6, 7, 8, 9, 10, 11, 12],
+        ]);
+
+        let f: Vec<_> = segment(9, &payload(14)).unwrap().collect();
+        assert_eq!(f.len(), 3);
+        assert_eq!(f[2], [0x22, 13, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+
+        assert_eq!(segment(0, &payload(MAX_LEN)).unwrap().count(), 32);
+        assert!(segment(0, &payload(224)).is_err());
+    }
+
+    #[test]
+    fn reassemble() {
+        let t = Cell::new(0);
+        let mut r = Reassembler::<_, 8>::new(clock(&t));
+        for len in [0, 6, 7, 100, MAX_LEN] {
+            assert_eq!(feed(&mut r, id(0x1f805, 0x10), 1, &payload(len)), Some((0x1f805, 0x10, payload(len))));
+        }
+    }
+
+    #[test]
+    fn interleaved() {
+        let t = Cell::new(0);
+        let mut r = Reassembler::<_, 8>::new(clock(&t));
+        let a: Vec<_> = segment(0, &payload(30)).unwrap().collect();
+        let b: Vec<_> = segment(5, &[0xaa; 20]).unwrap().collect();
+
+        let mut res = Vec::new();
+        for (i, f) in a.iter().enumerate() {
+            if let Some(p) = r.on_frame(id(0x1f805, 0x10), f).unwrap() { res.push(p.data.to_vec()) }
+            if let Some(f) = b.get(i) {
+                if let Some(p) = r.on_frame(id(0x1f805, 0x20), f).unwrap() { res.push(p.data.to_vec()) }
+            }
+        }
+        assert_eq!(res, [std::vec![0xaa; 20], payload(30)]);
+    }
+
+    #[test]
+    fn err() {
+        let t = Cell::new(0);
+        let mut r = Reassembler::<_, 8>::new(clock(&t));
+        let f: Vec<_> = segment(0, &payload(30)).unwrap().collect();
+
+        // lost frame
+        r.on_frame(id(0x1f805, 0x10), &f[0]).unwrap();
+        assert_eq!(r.on_frame(id(0x1f805, 0x10), &f[2]), Err(Error::SeqMismatch(1, 2)));
+        assert_eq!(r.on_frame(id(0x1f805, 0x10), &f[3]), Ok(None));
+
+        // timeout
+        r.on_frame(id(0x1f805, 0x10), &f[0]).unwrap();
+        t.set(TIMEOUT);
+        assert_eq!(r.on_frame(id(0x1f805, 0x10), &f[1]), Ok(None));
+
+        assert_eq!(r.on_frame(id(0x1f805, 0x10), &[0x00, 224, 0, 0, 0, 0, 0, 0]), Err(Error::TooLong(224)));
+    }
+
+    #[test]
+    fn decode() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Gnss { sid: u8, days: u16, lat: i32 }
+        impl Message for Gnss { const PGN: Pgn = 0x1f805; }
+
+        let t = Cell::new(0);
+        let mut r = Reassembler::<_, 8>::new(clock(&t));
+        let mut decode = |data: &[u8]| {
+            let (pgn, src, data) = feed(&mut r, id(0x1f805, 0x10), 0, data)?;
+            Some(FastPacket { pgn, src, dst: 0xff, data: &data }.decode::<Gnss>())
+        };
+
+        let mut data = std::vec![0x01, 0x4b, 0x3c, 0x12, 0x34, 0x56, 0x78];
+        assert_eq!(decode(&data), Some(Ok(Gnss { sid: 1, days: 0x4b3c, lat: 0x1234_5678 })));
+
+        // rest of payload is not dropped
+        data.extend_from_slice(&[0; 36]);
+        assert_eq!(decode(&data), Some(Err(j1939::Error::PayloadTooLong(43))));
+    }
+}
@@ -1,25 +1,46 @@
 use core::default::Default;
 use core::marker::PhantomData;
+use core::ops::RangeInclusive;
 
-/* | xxxx |   yyyyy   |  zzzz  |   -> total 29bits or 11bits
-     BASE   (node_id)  (msg_id)
+/* | xxxx |   pp   |   ddddd   |   yyyyy   |  zzzz  |   -> total 29bits or 11bits
+     BASE   (prio)     (dst)     (node_id)  (msg_id)
+
+   prio is optional, PRIO_LEN = 0 by default, lower value wins arbitration
+   dst is optional (PEER = true), same length as node_id, all ones for broadcast
  */
 
-use core::any::{TypeId, Any};
-use embedded_can::{Frame, Id, ExtendedId};
-use super::{from_frame, to_frame};
+use embedded_can::{Frame, Id, ExtendedId, StandardId};
+use super::{from_frame, to_frame, MaxSize};
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, thiserror_no_std::Error, PartialEq)]
 pub enum Error {
    #[error("node_id out of range, {0} > 2**{1}")]
     EncNodeIdOutOfRange(u32, usize),
+    #[error("priority out of range, {0} > 2**{1}")]
+    EncPriorityOutOfRange(u32, usize),
+    #[error("msg_id out of range, {0} > 2**{1}")]
+    EncMsgIdOutOfRange(u32, usize),
     #[error("can msg id of {0} out of range")]
     EncCanIdOutOfRange(u32),
     #[error("msg_id mismatch, {0} != {1}")]
     DecMsgIdMismatch(u32, u32),
     #[error("not this node group")]
     DecNodeGroupMismatch,
+    #[error("unknown msg_id {0}")]
+    DecUnknownMsgId(u32),
+    #[error("sum of prio, node_id & msg_id len {0} > {1}")]
+    LayoutTooLong(usize, usize),
+    #[error("base {0:#x} out of range")]
+    LayoutBaseOutOfRange(u32),
+    #[error("base {0:#x} overlaps prio, node_id or msg_id part")]
+    LayoutBaseOverlap(u32),
+    #[error("msg_id {0} not fit in {1} bits")]
+    LayoutMsgIdOverflow(u32, usize),
+    #[error("layout has no dst field")]
+    LayoutNoDst,
+    #[error("empty msg list of filter")]
+    SelectEmpty,
     #[error("frame error: {0}")]
     FrameErr(crate::frame::Error),
     #[error("serde error: {0}")]
@@ -28,200 +49,1033 @@ pub enum Error {
 
 // -------------------------------------- msg list
 pub trait List: private::Sealed {
-    fn msg_id<T: Any>() -> i32;
     const LEN: usize;
+    /// max msg_id + 1, reserved ids included
+    const END: u32;
+
+    fn msg_id<X: Elem<Self>>() -> u32 where Self: Sized { X::MSG_ID }
+
+    /// whether `msg_id` is of a msg in the list
+    fn has_msg_id(msg_id: u32) -> bool;
 }
 
-pub trait Elem<T: List> {}
+/// msg of list `T`
+pub trait Elem<T: List> {
+    const MSG_ID: u32;
+    /// default priority
+    const PRIORITY: u32 = 0;
+}
 
 mod private {
     use super::{List, Cons, Nil};
-    use core::any::Any;
 
     pub trait Sealed {}
     impl Sealed for Nil {}
-    impl <H: Any, T: List> Sealed for Cons<H, T> {}
+    impl <H, T: List, const ID: u32> Sealed for Cons<H, T, ID> {}
+    impl <T: List, const END: u32> Sealed for super::Reserved<T, END> {}
 }
 
 #[derive(Default, Debug)]
 pub struct Nil;
 impl List for Nil {
     const LEN: usize = 0;
+    const END: u32 = 0;
 
-    fn msg_id<T: Any>() -> i32 { -1 }
+    fn has_msg_id(_msg_id: u32) -> bool { false }
 }
 
 #[derive(Default, Debug)]
-pub struct Cons<H: Any, T: List> {
+pub struct Cons<H, T: List, const ID: u32> {
     _phantom: PhantomData<(H,T)>
 }
 
-impl <H: Any, T: List> List for Cons<H, T> {
+impl <H, T: List, const ID: u32> List for Cons<H, T, ID> {
     const LEN: usize = T::LEN + 1;
+    const END: u32 = if ID + 1 > T::END { ID + 1 } else { T::END };
+
+    fn has_msg_id(msg_id: u32) -> bool { msg_id == ID || T::has_msg_id(msg_id) }
+}
+
+/// list `T` with reserved ids below `END`, so they are range checked as msg_id
+#[derive(Default, Debug)]
+pub struct Reserved<T: List, const END: u32> {
+    _phantom: PhantomData<T>
+}
+
+impl <T: List, const END: u32> List for Reserved<T, END> {
+    const LEN: usize = T::LEN;
+    const END: u32 = if END > T::END { END } else { T::END };
+
+    fn has_msg_id(msg_id: u32) -> bool { T::has_msg_id(msg_id) }
+}
+
+#[doc(hidden)]
+pub const fn max(xs: &[u32]) -> u32 {
+    let mut res = 0;
+    let mut i = 0;
+    while i < xs.len() {
+        if xs[i] > res { res = xs[i] }
+        i += 1;
+    }
+    res
+}
 
-    fn msg_id<X: Any>() -> i32 {
-        if TypeId::of::<X>() == TypeId::of::<H>() { return 0 as i32 }
-        let mut r = T::msg_id::<X>();
-        if r >= 0 { r += 1 }
-        r
+/// max id + 1
+#[doc(hidden)]
+pub const fn reserved_end(ids: &[u32]) -> u32 {
+    let mut res = 0;
+    let mut i = 0;
+    while i < ids.len() {
+        if ids[i] + 1 > res { res = ids[i] + 1 }
+        i += 1;
     }
+    res
 }
 
+/// panic if there is duplicate msg_id
+#[doc(hidden)]
+pub const fn check_msg_ids(ids: &[u32]) {
+    let mut i = 0;
+    while i < ids.len() {
+        let mut j = i + 1;
+        while j < ids.len() {
+            assert!(ids[i] != ids[j], "duplicate msg_id");
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+// head is `fn(H)', so msg with lifetime (`Msg<'_>') can be in the list
 #[macro_export]
 macro_rules! node_group_msg_list {
+    [ @ $n: expr, [] ] => { Nil };
+
+    [ @ $n: expr, [$h: ty $(, $t: ty )*] ] => {
+        Cons<fn($h), $crate::node_group_msg_list![@ $n + 1, [$( $t ),*]], { $n }>
+    };
+
     [] => { Nil };
 
-    [ $head:ty $(,)? ] => { Cons<$head, Nil> };
+    [ $( $e: ty ),+ $(,)? ] => { $crate::node_group_msg_list![@ 0, [$( $e ),+]] };
 
-    [ $head:ty, $( $tail:ty ),+ $(,)? ] => {
-        Cons<$head, $crate::node_group_msg_list![$( $tail ),+]>
+    [ $h: ty = $id: expr $(, $t: ty = $tid: expr )* $(,)? ] => {
+        Cons<fn($h), $crate::node_group_msg_list![$( $t = $tid ),*], { $id }>
     };
 }
 
 #[macro_export]
 macro_rules! node_group_msg_impl_elem {
-    ( $tp: ident, [$h: ty $(,)? ]) => {
-        impl Elem<$tp> for $h {}
+    ( @ $n: expr, $tp: ident, [] ) => {};
+
+    ( @ $n: expr, $tp: ident, [$(#[prio = $p: expr])? $h: ty $(, $(#[prio = $tp_: expr])? $t: ty )*] ) => {
+        impl Elem<$tp> for $h { const MSG_ID: u32 = $n; $( const PRIORITY: u32 = $p; )? }
+        $crate::node_group_msg_impl_elem!{@ $n + 1, $tp, [$( $(#[prio = $tp_])? $t ),*]}
     };
 
-    ( $tp: ident, [$h: ty, $( $t: ty ),* $(,)? ] ) => {
-        impl Elem<$tp> for $h {}
-        $crate::node_group_msg_impl_elem!{$tp, [$( $t ),*]}
-    }
+    ( $tp: ident, [$( $(#[prio = $p: expr])? $e: ty ),* $(,)? ] ) => {
+        $crate::node_group_msg_impl_elem!{@ 0, $tp, [$( $(#[prio = $p])? $e ),*]}
+    };
+
+    ( $tp: ident, [$( $(#[prio = $p: expr])? $e: ty = $id: expr ),* $(,)? ] ) => {
+        $( impl Elem<$tp> for $e { const MSG_ID: u32 = $id; $( const PRIORITY: u32 = $p; )? } )*
+    };
 }
 
+/// `node_group_msg_def!(T, [A, B])` define msg list `T`, msg_id is the position,
+/// `node_group_msg_def!(T, [A = 0, B = 3], reserved [1, 2])` with explicit msg_id,
+/// reserved ids can not be used, and
+/// `node_group_msg_def!(T, pub enum Msg { A(A), B(B) })` also define enum `Msg` of all msgs,
+/// explicit msg_id is also accepted, e.g. `{ A(A) = 0, B(B) = 3 }`.
+/// Default priority of msg is set by `#[prio = 1]` before it, e.g. `[#[prio = 1] A, B]`
 #[macro_export]
 macro_rules! node_group_msg_def {
-    ( $tp: ident, [$( $e: ty ),* $(,)? ] ) => {
+    ( @enum $tp: ident, $(#[$m: meta])* $vis: vis enum $en: ident { $( $v: ident ( $e: ty ) ),* } ) => {
+        $(#[$m])*
+        $vis enum $en { $( $v($e) ),* }
+
+        impl $crate::node_group::MsgEnum<$tp> for $en {
+            const MAX_PRIORITY: u32 = $crate::node_group::max(&[$( <$e as $crate::node_group::Elem<$tp>>::PRIORITY ),*]);
+
+            fn msg_id(&self) -> $crate::node_group::MsgId {
+                match self {
+                    $( $en::$v(_) => $crate::node_group::MsgId(<$e as $crate::node_group::Elem<$tp>>::MSG_ID), )*
+                }
+            }
+
+            fn priority(&self) -> u32 {
+                match self {
+                    $( $en::$v(_) => <$e as $crate::node_group::Elem<$tp>>::PRIORITY, )*
+                }
+            }
+
+            fn from_bytes(msg_id: $crate::node_group::MsgId, data: &[u8]) -> Option<$crate::Result<Self>> {
+                $(
+                    if msg_id.0 == <$e as $crate::node_group::Elem<$tp>>::MSG_ID {
+                        return Some($crate::from_bytes::<$e>(data).map($en::$v));
+                    }
+                )*
+                None
+            }
+
+            fn to_bytes(&self) -> $crate::Result<$crate::node_group::Bytes> {
+                match self {
+                    $( $en::$v(x) => $crate::to_bytes(x), )*
+                }
+            }
+        }
+    };
+
+    ( $tp: ident, [$( $(#[prio = $p: expr])? $e: ty ),* $(,)? ] ) => {
         type $tp = $crate::node_group_msg_list![$( $e ),*];
-        $crate::node_group_msg_impl_elem!{$tp, [$( $e ),*]}
-    }
+        $crate::node_group_msg_impl_elem!{$tp, [$( $(#[prio = $p])? $e ),*]}
+    };
+
+    ( $tp: ident, [$( $(#[prio = $p: expr])? $e: ty = $id: expr ),* $(,)? ] ) => {
+        type $tp = $crate::node_group_msg_list![$( $e = $id ),*];
+        $crate::node_group_msg_impl_elem!{$tp, [$( $(#[prio = $p])? $e = $id ),*]}
+        const _: () = $crate::node_group::check_msg_ids(&[$( $id ),*]);
+    };
+
+    // reserved ids count in `END`, so `NodeGroup` checks them against `MSG_ID_LEN` as other ids
+    ( $tp: ident, [$( $(#[prio = $p: expr])? $e: ty = $id: expr ),* $(,)? ], reserved [$( $r: expr ),* $(,)? ] ) => {
+        type $tp = $crate::node_group::Reserved<$crate::node_group_msg_list![$( $e = $id ),*],
+                                                 { $crate::node_group::reserved_end(&[$( $r ),*]) }>;
+        $crate::node_group_msg_impl_elem!{$tp, [$( $(#[prio = $p])? $e = $id ),*]}
+        const _: () = $crate::node_group::check_msg_ids(&[$( $id, )* $( $r ),*]);
+    };
+
+    ( $tp: ident, $(#[$m: meta])* $vis: vis enum $en: ident { $( $(#[prio = $p: expr])? $v: ident ( $e: ty ) ),* $(,)? } ) => {
+        $crate::node_group_msg_def!($tp, [$( $(#[prio = $p])? $e ),*]);
+        $crate::node_group_msg_def!(@enum $tp, $(#[$m])* $vis enum $en { $( $v($e) ),* });
+    };
+
+    ( $tp: ident, $(#[$m: meta])* $vis: vis enum $en: ident { $( $(#[prio = $p: expr])? $v: ident ( $e: ty ) = $id: expr ),* $(,)? }
+      $(, reserved [$( $r: expr ),* $(,)? ] )? ) => {
+        $crate::node_group_msg_def!($tp, [$( $(#[prio = $p])? $e = $id ),*] $(, reserved [$( $r ),*])?);
+        $crate::node_group_msg_def!(@enum $tp, $(#[$m])* $vis enum $en { $( $v($e) ),* });
+    };
 }
 
-// ---------------------------------- node group
-type NodeId = u32;
-type MsgId  = u32;
+pub type Bytes = heapless::Vec<u8, 8>;
 
+/// enum of all msgs of list `L`, generated by `node_group_msg_def!`
+pub trait MsgEnum<L: List>: Sized {
+    /// max default priority of all msgs
+    const MAX_PRIORITY: u32;
 
-#[derive(Default, Debug)]
-pub struct NodeGroup<L: List, const BASE: u32, const NODE_ID_LEN: usize, const MSG_ID_LEN: usize> {
-    pub name: &'static str,
-    _phantom: PhantomData<L>,
+    fn msg_id(&self) -> MsgId;
+    fn priority(&self) -> u32;
+    /// `None` if `msg_id` not in `L`
+    fn from_bytes(msg_id: MsgId, data: &[u8]) -> Option<crate::Result<Self>>;
+    fn to_bytes(&self) -> crate::Result<Bytes>;
 }
 
-impl <L: List, const BASE: u32, const NODE_ID_LEN: usize, const MSG_ID_LEN: usize>
-    NodeGroup<L, BASE, NODE_ID_LEN, MSG_ID_LEN>
-{
+pub type NodeId = u32;
 
-    const MSG_ID_MASK: u32  = ((1 << MSG_ID_LEN) - 1) as u32;
-    const NODE_ID_MASK: u32 = (((1 << NODE_ID_LEN) - 1) as u32) << MSG_ID_LEN;
-    const BASE_MASK: u32    = !(Self::MSG_ID_MASK | Self::NODE_ID_MASK);
+/// msg_id part of can id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MsgId(pub u32);
 
-    pub fn msg_id<X: Any + Elem<L>>() -> i32 { L::msg_id::<X>() }
 
-    fn id2raw(id: &Id) -> u32 {
+// ---------------------------------- filter
+/// frames an acceptance filter should pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Select<'a> {
+    Group,
+    Node(NodeId),
+    /// msg of any node
+    Msg(MsgId),
+    NodeMsg(NodeId, MsgId),
+    /// any of the msgs of any node, should not be empty
+    Msgs(&'a [MsgId]),
+    /// msgs to node, of layout with dst field, broadcast msgs are not included
+    To(NodeId),
+}
+
+/// mask filter, `id` passes if `id & mask == self.id & mask`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaskFilter {
+    pub id: u32,
+    pub mask: u32,
+    /// extended id or standard id
+    pub ext: bool,
+}
+
+impl MaskFilter {
+    pub fn matches(&self, id: &Id) -> bool {
         match id {
-            Id::Standard(x) => x.as_raw() as u32,
-            Id::Extended(x) => x.as_raw()
+            Id::Standard(x) if !self.ext => (x.as_raw() as u32 ^ self.id) & self.mask == 0,
+            Id::Extended(x) if self.ext => (x.as_raw() ^ self.id) & self.mask == 0,
+            _ => false,
         }
     }
 
-    pub const fn new(name: &'static str) -> Self {
-        assert!(NODE_ID_LEN + MSG_ID_LEN <= 29,
-                "sum of NODE_ID_LEN & MSG_ID_LEN too large");
-        assert!(BASE & (Self::MSG_ID_MASK | Self::NODE_ID_MASK) == 0,
-                "msg_id part & node_id part of `BASE' should be 0");
-        assert!(L::LEN <= (1 << MSG_ID_LEN),
-                "number of msg should fit within MSG_ID_LEN bits");
+    /// smallest range filter containing ids passed by the mask filter,
+    /// exact if all cleared bits of mask are the lowest ones
+    pub fn range(&self) -> RangeInclusive<u32> {
+        let all = if self.ext { 0x1fff_ffff } else { 0x7ff };
+        let low = self.id & self.mask & all;
+        low..=(low | (!self.mask & all))
+    }
+}
 
-        assert!(BASE & 0xe000_0000 == 0, "BASE length > 29");
+// ---------------------------------- layout
+/// bit layout of can id, in const params of `NodeGroup`, or chosen at runtime for `DynNodeGroup`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    base: u32,
+    node_id_len: usize,
+    msg_id_len: usize,
+    prio_len: usize,
+    /// dst field, of `node_id_len` bits
+    peer: bool,
+}
 
-        Self { name, _phantom: PhantomData {} }
+impl Layout {
+    /// check that ids fit in 29 bits
+    pub const fn new(base: u32, node_id_len: usize, msg_id_len: usize, prio_len: usize) -> Result<Self, Error> {
+        Layout { base, node_id_len, msg_id_len, prio_len, peer: false }.check(29)
+    }
+
+    /// check that ids fit in 11 bits
+    pub const fn new_std(base: u32, node_id_len: usize, msg_id_len: usize, prio_len: usize) -> Result<Self, Error> {
+        Layout { base, node_id_len, msg_id_len, prio_len, peer: false }.check(11)
+    }
+
+    /// same as `new`, with dst field
+    pub const fn new_peer(base: u32, node_id_len: usize, msg_id_len: usize, prio_len: usize) -> Result<Self, Error> {
+        Layout { base, node_id_len, msg_id_len, prio_len, peer: true }.check(29)
+    }
+
+    /// same as `new_std`, with dst field
+    pub const fn new_peer_std(base: u32, node_id_len: usize, msg_id_len: usize, prio_len: usize) -> Result<Self, Error> {
+        Layout { base, node_id_len, msg_id_len, prio_len, peer: true }.check(11)
+    }
+
+    const fn check(self, bits: usize) -> Result<Self, Error> {
+        let len = self.prio_len.saturating_add(self.node_id_len).saturating_add(self.msg_id_len)
+            .saturating_add(self.dst_len());
+        if len > bits { return Err(Error::LayoutTooLong(len, bits)) }
+        if self.base >= (1 << bits) { return Err(Error::LayoutBaseOutOfRange(self.base)) }
+        if self.base & !self.base_mask() != 0 { return Err(Error::LayoutBaseOverlap(self.base)) }
+        Ok(self)
+    }
+
+    pub const fn base(&self) -> u32 { self.base }
+    pub const fn node_id_len(&self) -> usize { self.node_id_len }
+    pub const fn msg_id_len(&self) -> usize { self.msg_id_len }
+    pub const fn prio_len(&self) -> usize { self.prio_len }
+    pub const fn peer(&self) -> bool { self.peer }
+
+    const fn dst_len(&self) -> usize {
+        if self.peer { self.node_id_len } else { 0 }
+    }
+
+    /// dst of msgs to all nodes
+    pub const fn broadcast(&self) -> NodeId {
+        ((1u64 << self.node_id_len) - 1) as u32
     }
 
-    pub fn encode_ext<F: Frame, X: Serialize + Any + Elem<L>>(node_id: NodeId, x: &X) -> Result<F, Error> {
-        let msg_id = Self::msg_id::<X>();
+    pub const fn msg_id_mask(&self) -> u32 {
+        ((1u64 << self.msg_id_len) - 1) as u32
+    }
 
-        let can_id = BASE | (node_id << (MSG_ID_LEN as u32)) | (msg_id as u32);
+    pub const fn node_id_mask(&self) -> u32 {
+        (((1u64 << self.node_id_len) - 1) as u32) << self.msg_id_len
+    }
+
+    pub const fn dst_mask(&self) -> u32 {
+        (((1u64 << self.dst_len()) - 1) as u32) << (self.node_id_len + self.msg_id_len)
+    }
+
+    pub const fn prio_mask(&self) -> u32 {
+        (((1u64 << self.prio_len) - 1) as u32) << (self.node_id_len + self.msg_id_len + self.dst_len())
+    }
+
+    pub const fn base_mask(&self) -> u32 {
+        !(self.msg_id_mask() | self.node_id_mask() | self.dst_mask() | self.prio_mask())
+    }
+
+    fn can_id(&self, node_id: NodeId, msg_id: MsgId, prio: u32) -> Result<u32, Error> {
+        self.can_id_to(self.broadcast(), node_id, msg_id, prio)
+    }
+
+    // `dst` is ignored without dst field
+    fn can_id_to(&self, dst: NodeId, node_id: NodeId, msg_id: MsgId, prio: u32) -> Result<u32, Error> {
+        for n in [node_id, dst] {
+            if n as u64 >= (1 << self.node_id_len) {
+                return Err(Error::EncNodeIdOutOfRange(n, self.node_id_len))
+            }
+        }
+        if prio as u64 >= (1 << self.prio_len) {
+            return Err(Error::EncPriorityOutOfRange(prio, self.prio_len))
+        }
+        if msg_id.0 > self.msg_id_mask() {
+            return Err(Error::EncMsgIdOutOfRange(msg_id.0, self.msg_id_len))
+        }
+
+        let dst = if self.peer { dst << (self.node_id_len + self.msg_id_len) } else { 0 };
+        let prio = prio << (self.node_id_len + self.msg_id_len + self.dst_len());
+        Ok(self.base | prio | dst | (node_id << self.msg_id_len) | msg_id.0)
+    }
+
+    fn ext_id(&self, node_id: NodeId, msg_id: MsgId, prio: u32) -> Result<Id, Error> {
+        self.ext_id_to(self.broadcast(), node_id, msg_id, prio)
+    }
+
+    fn ext_id_to(&self, dst: NodeId, node_id: NodeId, msg_id: MsgId, prio: u32) -> Result<Id, Error> {
+        let can_id = self.can_id_to(dst, node_id, msg_id, prio)?;
         let Some(ext_id) = ExtendedId::new(can_id) else {
             return Err(Error::EncCanIdOutOfRange(can_id))
         };
 
-        if node_id >= (1 << NODE_ID_LEN) {
-            return Err(Error::EncNodeIdOutOfRange(node_id, NODE_ID_LEN))
-        }
+        Ok(Id::Extended(ext_id))
+    }
 
-        to_frame(Id::Extended(ext_id), x).map_err(|x| match x {
-                crate::frame::Error::SerdeErr(e) => Error::SerdeErr(e),
-                _ => Error::FrameErr(x),
-        })
+    fn std_id(&self, node_id: NodeId, msg_id: MsgId, prio: u32) -> Result<Id, Error> {
+        let can_id = self.can_id(node_id, msg_id, prio)?;
+        let Some(std_id) = u16::try_from(can_id).ok().and_then(StandardId::new) else {
+            return Err(Error::EncCanIdOutOfRange(can_id))
+        };
+
+        Ok(Id::Standard(std_id))
+    }
+
+    fn id2raw(id: &Id) -> u32 {
+        match id {
+            Id::Standard(x) => x.as_raw() as u32,
+            Id::Extended(x) => x.as_raw()
+        }
     }
 
-    fn extract(id: &Id) -> Option<(NodeId, MsgId)> {
+    /// node_id & msg_id of `id`, `None` if not this layout
+    pub fn classify(&self, id: &Id) -> Option<(NodeId, MsgId)> {
         let id_raw = Self::id2raw(id);
 
-        if (id_raw & Self::BASE_MASK) != BASE { return None }
-        let msg_id = id_raw & Self::MSG_ID_MASK;
-        let node_id = (id_raw & Self::NODE_ID_MASK) >> MSG_ID_LEN;
+        if (id_raw & self.base_mask()) != self.base { return None }
+        let msg_id = MsgId(id_raw & self.msg_id_mask());
+        let node_id = (id_raw & self.node_id_mask()) >> self.msg_id_len;
 
         Some((node_id, msg_id))
     }
 
-    pub fn decode<'a, T: Any + Deserialize<'a> + Elem<L>, F: Frame>(f: &'a F) -> Result<(NodeId, T), Error> {
-        let Some((node_id, msg_id)) = Self::extract(&f.id()) else {
-            return Err(Error::DecNodeGroupMismatch);
+    /// priority of `id`, `None` if not this layout
+    pub fn priority(&self, id: &Id) -> Option<u32> {
+        let id_raw = Self::id2raw(id);
+
+        if (id_raw & self.base_mask()) != self.base { return None }
+        Some((id_raw & self.prio_mask()) >> (self.node_id_len + self.msg_id_len + self.dst_len()))
+    }
+
+    /// node_id, dst & msg_id of `id`, dst is broadcast without dst field
+    pub fn classify_peer(&self, id: &Id) -> Option<(NodeId, NodeId, MsgId)> {
+        let (node_id, msg_id) = self.classify(id)?;
+        if !self.peer { return Some((node_id, self.broadcast(), msg_id)) }
+
+        let dst = (Self::id2raw(id) & self.dst_mask()) >> (self.node_id_len + self.msg_id_len);
+        Some((node_id, dst, msg_id))
+    }
+
+    fn check_select<L: List>(&self, sel: Select<'_>) -> Result<(), Error> {
+        let (node_id, msgs): (_, &[MsgId]) = match sel {
+            Select::Group => (None, &[]),
+            Select::Node(n) => (Some(n), &[]),
+            Select::Msg(ref m) => (None, core::slice::from_ref(m)),
+            Select::NodeMsg(n, ref m) => (Some(n), core::slice::from_ref(m)),
+            Select::Msgs([]) => return Err(Error::SelectEmpty),
+            Select::Msgs(ms) => (None, ms),
+            Select::To(_) if !self.peer => return Err(Error::LayoutNoDst),
+            Select::To(n) => (Some(n), &[]),
         };
 
-        if msg_id != Self::msg_id::<T>() as u32 {
-            return Err(Error::DecMsgIdMismatch(msg_id, Self::msg_id::<T>() as u32));
+        if let Some(n) = node_id.filter(|n| *n as u64 >= (1 << self.node_id_len)) {
+            return Err(Error::EncNodeIdOutOfRange(n, self.node_id_len))
         }
+        match msgs.iter().find(|m| !L::has_msg_id(m.0)) {
+            Some(m) => Err(Error::DecUnknownMsgId(m.0)),
+            None => Ok(()),
+        }
+    }
+
+    fn mask_filter<L: List>(&self, sel: Select<'_>) -> Result<(u32, u32), Error> {
+        self.check_select::<L>(sel)?;
+
+        let (base, base_mask) = (self.base, self.base_mask());
+        let node = |n: NodeId| n << self.msg_id_len;
+        Ok(match sel {
+            Select::Group => (base, base_mask),
+            Select::Node(n) => (base | node(n), base_mask | self.node_id_mask()),
+            Select::Msg(m) => (base | m.0, base_mask | self.msg_id_mask()),
+            Select::NodeMsg(n, m) => (base | node(n) | m.0, base_mask | self.node_id_mask() | self.msg_id_mask()),
+            Select::Msgs(ms) => {
+                // keep bits common to all msg_ids
+                let first = ms.first().map_or(0, |m| m.0);
+                let diff = ms.iter().fold(0, |acc, m| acc | (m.0 ^ first));
+                let mask = self.msg_id_mask() & !diff;
+                (base | (first & mask), base_mask | mask)
+            },
+            Select::To(n) => (base | (n << (self.node_id_len + self.msg_id_len)), base_mask | self.dst_mask()),
+        })
+    }
+
+    fn mask_filter_ext<L: List>(&self, sel: Select<'_>) -> Result<MaskFilter, Error> {
+        let (id, mask) = self.mask_filter::<L>(sel)?;
+        Ok(MaskFilter { id, mask: mask & 0x1fff_ffff, ext: true })
+    }
+
+    fn mask_filter_std<L: List>(&self, sel: Select<'_>) -> Result<MaskFilter, Error> {
+        let (id, mask) = self.mask_filter::<L>(sel)?;
+        Ok(MaskFilter { id, mask: mask & 0x7ff, ext: false })
+    }
+
+    fn list_filter<L: List>(self, sel: Select<'_>, prio: u32) -> Result<impl Iterator<Item = u32> + '_, Error> {
+        self.check_select::<L>(sel)?;
+        let dst = if let Select::To(n) = sel { n } else { self.broadcast() };
+        let base = self.can_id_to(dst, 0, MsgId(0), prio)?;
 
-        let res = from_frame::<T, F>(&f).map_err(|err| match err {
+        let shift = self.msg_id_len;
+        let nodes = match sel {
+            Select::Node(n) | Select::NodeMsg(n, _) => n..=n,
+            _ => 0..=(self.node_id_mask() >> shift),
+        };
+        let has = move |m: u32| match sel {
+            Select::Msg(x) | Select::NodeMsg(_, x) => x.0 == m,
+            Select::Msgs(xs) => xs.contains(&MsgId(m)),
+            _ => L::has_msg_id(m),
+        };
+
+        Ok(nodes.flat_map(move |n| (0..L::END).filter(move |m| has(*m)).map(move |m| base | (n << shift) | m)))
+    }
+}
+
+fn encode<F: Frame, X: Serialize>(id: Id, x: &X) -> Result<F, Error> {
+    to_frame(id, x).map_err(|x| match x {
             crate::frame::Error::SerdeErr(e) => Error::SerdeErr(e),
-            _ => Error::FrameErr(err),
-        })?;
+            _ => Error::FrameErr(x),
+    })
+}
 
-        Ok((node_id, res))
+fn encode_any<F: Frame, L: List, E: MsgEnum<L>>(id: Id, x: &E) -> Result<F, Error> {
+    let data = x.to_bytes().map_err(Error::SerdeErr)?;
+    F::new(id, &data).ok_or(Error::FrameErr(crate::frame::Error::MsgTooLong))
+}
+
+fn request<F: Frame, X: MaxSize>(id: Id) -> Result<F, Error> {
+    F::new_remote(id, X::MAX_SIZE).ok_or(Error::FrameErr(crate::frame::Error::MsgTooLong))
+}
+
+fn decode<'a, T: Deserialize<'a>, F: Frame>(layout: &Layout, msg_id: MsgId, f: &'a F) -> Result<(NodeId, T), Error> {
+    let Some((node_id, id)) = layout.classify(&f.id()) else {
+        return Err(Error::DecNodeGroupMismatch);
+    };
+
+    if id != msg_id {
+        return Err(Error::DecMsgIdMismatch(id.0, msg_id.0));
     }
+
+    let res = from_frame::<T, F>(f).map_err(|err| match err {
+        crate::frame::Error::SerdeErr(e) => Error::SerdeErr(e),
+        _ => Error::FrameErr(err),
+    })?;
+
+    Ok((node_id, res))
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    extern crate std;
+fn decode_peer<'a, T: Deserialize<'a>, F: Frame>(layout: &Layout, msg_id: MsgId, f: &'a F)
+                                                 -> Result<(NodeId, NodeId, T), Error> {
+    if !layout.peer { return Err(Error::LayoutNoDst) }
 
-    #[derive(Debug, PartialEq)]
-    struct Frame { id: Id, data: [u8; 8], dlc: usize, remote: bool }
-    impl embedded_can::Frame for Frame {
-        fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
-            if data.len() > 8 { return None }
-            let mut res_data = [0u8; 8];
-            for i in 0..data.len() { res_data[i] = data[i]; }
-            let res_dlc = data.len();
-            Some(Frame {id: id.into(), data: res_data, dlc: res_dlc, remote: false})
-        }
+    let (node_id, x) = decode(layout, msg_id, f)?;
+    let dst = layout.classify_peer(&f.id()).map_or(layout.broadcast(), |x| x.1);
+    Ok((node_id, dst, x))
+}
 
-        fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
-            if dlc > 8 { return None }
-            Some(Frame {id: id.into(), data: [0u8;8], dlc, remote: true})
+fn decode_any_peer<L: List, E: MsgEnum<L>, F: Frame>(layout: &Layout, f: &F) -> Result<(NodeId, NodeId, E), Error> {
+    if !layout.peer { return Err(Error::LayoutNoDst) }
+
+    let (node_id, x) = decode_any(layout, f)?;
+    let dst = layout.classify_peer(&f.id()).map_or(layout.broadcast(), |x| x.1);
+    Ok((node_id, dst, x))
+}
+
+fn decode_any<L: List, E: MsgEnum<L>, F: Frame>(layout: &Layout, f: &F) -> Result<(NodeId, E), Error> {
+    let Some((node_id, msg_id)) = layout.classify(&f.id()) else {
+        return Err(Error::DecNodeGroupMismatch);
+    };
+
+    if f.is_remote_frame() {
+        return Err(Error::FrameErr(crate::frame::Error::RemoteFrame));
+    }
+
+    match E::from_bytes(msg_id, f.data()) {
+        Some(res) => Ok((node_id, res.map_err(Error::SerdeErr)?)),
+        None => Err(Error::DecUnknownMsgId(msg_id.0)),
+    }
+}
+
+// ---------------------------------- id space
+/// ids `x` with `x & mask == id & mask`, of a group or a fixed id msg,
+/// standard & extended ids never overlap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdSpace {
+    pub name: &'static str,
+    pub id: u32,
+    pub mask: u32,
+    /// extended id or standard id
+    pub ext: bool,
+}
+
+impl IdSpace {
+    /// all extended ids of group, e.g. `IdSpace::group("motor", G::LAYOUT)`
+    pub const fn group(name: &'static str, layout: Layout) -> Self {
+        IdSpace { name, id: layout.base(), mask: layout.base_mask() & 0x1fff_ffff, ext: true }
+    }
+
+    /// same as `group` for standard ids
+    pub const fn group_std(name: &'static str, layout: Layout) -> Self {
+        IdSpace { name, id: layout.base(), mask: layout.base_mask() & 0x7ff, ext: false }
+    }
+
+    pub const fn fixed(name: &'static str, id: u32) -> Self {
+        IdSpace { name, id, mask: 0x1fff_ffff, ext: true }
+    }
+
+    pub const fn fixed_std(name: &'static str, id: u32) -> Self {
+        IdSpace { name, id, mask: 0x7ff, ext: false }
+    }
+
+    pub const fn overlaps(&self, other: &IdSpace) -> bool {
+        self.ext == other.ext && (self.id ^ other.id) & self.mask & other.mask == 0
+    }
+}
+
+/// two overlapping id spaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collision {
+    pub a: IdSpace,
+    pub b: IdSpace,
+}
+
+impl Collision {
+    /// an id in both spaces
+    pub fn example(&self) -> u32 {
+        (self.a.id & self.a.mask) | (self.b.id & self.b.mask)
+    }
+}
+
+impl core::fmt::Display for Collision {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "`{}` (id {:#x}, mask {:#x}) overlaps `{}` (id {:#x}, mask {:#x}), e.g. id {:#x}",
+               self.a.name, self.a.id, self.a.mask, self.b.name, self.b.id, self.b.mask, self.example())
+    }
+}
+
+/// index of first pair of overlapping spaces
+pub const fn find_overlap(spaces: &[IdSpace]) -> Option<(usize, usize)> {
+    let mut i = 0;
+    while i < spaces.len() {
+        let mut j = i + 1;
+        while j < spaces.len() {
+            if spaces[i].overlaps(&spaces[j]) { return Some((i, j)) }
+            j += 1;
         }
+        i += 1;
+    }
+    None
+}
 
-        fn is_extended(&self) -> bool {
-            match self.id {
-                embedded_can::Id::Extended(_) => true,
-                _ => false
-            }
+/// all pairs of overlapping spaces, for a readable report in test
+pub fn collisions<'a>(spaces: &'a [IdSpace]) -> impl Iterator<Item = Collision> + 'a {
+    spaces.iter().enumerate()
+        .flat_map(move |(i, a)| spaces[i + 1..].iter().map(move |b| Collision { a: *a, b: *b }))
+        .filter(|x| x.a.overlaps(&x.b))
+}
+
+/// panic if any spaces overlap, e.g. `const _: () = check_id_spaces(&[..]);` fails to compile
+pub const fn check_id_spaces(spaces: &[IdSpace]) {
+    let Some((i, j)) = find_overlap(spaces) else { return };
+
+    // const panic takes a single `&str' only
+    let parts = ["id space `", spaces[i].name, "` overlaps `", spaces[j].name, "`"];
+    let mut buf = [0u8; 128];
+    let mut len = 0;
+    let mut k = 0;
+    while k < parts.len() {
+        let p = parts[k].as_bytes();
+        let mut n = 0;
+        while n < p.len() && len < buf.len() {
+            buf[len] = p[n];
+            len += 1;
+            n += 1;
         }
+        k += 1;
+    }
+
+    match core::str::from_utf8(buf.split_at(len).0) {
+        Ok(msg) => panic!("{}", msg),
+        Err(_) => panic!("id space overlap"),
+    }
+}
+
+// ---------------------------------- node group
+#[derive(Default, Debug)]
+pub struct NodeGroup<L: List, const BASE: u32, const NODE_ID_LEN: usize, const MSG_ID_LEN: usize,
+                     const PRIO_LEN: usize = 0, const PEER: bool = false> {
+    pub name: &'static str,
+    _phantom: PhantomData<L>,
+}
+
+impl <L: List, const BASE: u32, const NODE_ID_LEN: usize, const MSG_ID_LEN: usize, const PRIO_LEN: usize,
+      const PEER: bool>
+    NodeGroup<L, BASE, NODE_ID_LEN, MSG_ID_LEN, PRIO_LEN, PEER>
+{
+    pub const LAYOUT: Layout = Layout {
+        base: BASE, node_id_len: NODE_ID_LEN, msg_id_len: MSG_ID_LEN, prio_len: PRIO_LEN, peer: PEER
+    };
+
+    /// dst of msgs to all nodes, with `PEER`
+    pub const BROADCAST: NodeId = Self::LAYOUT.broadcast();
+
+    pub(crate) const BASE_MASK: u32 = Self::LAYOUT.base_mask();
+
+    const ASSERT_MSG_ID: () = assert!(L::END as u64 <= (1 << MSG_ID_LEN),
+                                      "msg_id should fit within MSG_ID_LEN bits");
+
+    pub const fn msg_id<X: Elem<L>>() -> MsgId {
+        let () = Self::ASSERT_MSG_ID;
+        MsgId(X::MSG_ID)
+    }
+
+    pub const fn new(name: &'static str) -> Self {
+        assert!(PRIO_LEN + NODE_ID_LEN * (1 + PEER as usize) + MSG_ID_LEN <= 29,
+                "sum of PRIO_LEN, NODE_ID_LEN (twice with PEER) & MSG_ID_LEN too large");
+        assert!(BASE & !Self::BASE_MASK == 0,
+                "prio part, msg_id part & node_id part of `BASE' should be 0");
+        assert!(L::END as u64 <= (1 << MSG_ID_LEN),
+                "msg_id should fit within MSG_ID_LEN bits");
+
+        assert!(BASE & 0xe000_0000 == 0, "BASE length > 29");
+
+        Self { name, _phantom: PhantomData {} }
+    }
+
+    const ASSERT_STD: () = assert!(BASE < 0x800 && PRIO_LEN + NODE_ID_LEN * (1 + PEER as usize) + MSG_ID_LEN <= 11,
+                                   "BASE, PRIO_LEN, NODE_ID_LEN & MSG_ID_LEN should fit in 11 bits");
+
+    const ASSERT_PEER: () = assert!(PEER, "group should have dst field, `PEER = true'");
+
+    /// default priority of msg, checked at compile time
+    const fn prio<X: Elem<L>>() -> u32 {
+        let () = AssertPrio::<L, X, PRIO_LEN>::ELEM;
+        X::PRIORITY
+    }
+
+    /// same as `new`, and check at compile time that ids fit in 11 bits
+    pub const fn new_std(name: &'static str) -> Self {
+        let () = Self::ASSERT_STD;
+        Self::new(name)
+    }
 
-        fn is_remote_frame(&self) -> bool { self.remote }
-        fn id(&self) -> Id { self.id }
-        fn dlc(&self) -> usize { self.dlc }
-        fn data<'a>(&'a self) -> &'a [u8] {
-            if self.remote { return &[] }
-            &self.data[0..self.dlc]
+    pub fn encode_ext<F: Frame, X: Serialize + Elem<L>>(node_id: NodeId, x: &X) -> Result<F, Error> {
+        Self::encode_ext_prio(node_id, Self::prio::<X>(), x)
+    }
+
+    /// same as `encode_ext`, with priority `prio` instead of the default one of msg
+    pub fn encode_ext_prio<F: Frame, X: Serialize + Elem<L>>(node_id: NodeId, prio: u32, x: &X) -> Result<F, Error> {
+        encode(Self::LAYOUT.ext_id(node_id, Self::msg_id::<X>(), prio)?, x)
+    }
+
+    /// encode any msg of the group with extended id
+    pub fn encode_any<F: Frame, E: MsgEnum<L>>(node_id: NodeId, x: &E) -> Result<F, Error> {
+        let () = AssertPrio::<L, E, PRIO_LEN>::ENUM;
+        encode_any(Self::LAYOUT.ext_id(node_id, x.msg_id(), x.priority())?, x)
+    }
+
+    /// encode msg from node `node_id` to node `dst` with extended id, group should have `PEER`
+    pub fn encode_to<F: Frame, X: Serialize + Elem<L>>(dst: NodeId, node_id: NodeId, x: &X) -> Result<F, Error> {
+        Self::encode_to_prio(dst, node_id, Self::prio::<X>(), x)
+    }
+
+    /// same as `encode_to`, with priority `prio` instead of the default one of msg
+    pub fn encode_to_prio<F: Frame, X: Serialize + Elem<L>>(dst: NodeId, node_id: NodeId, prio: u32, x: &X)
+                                                            -> Result<F, Error> {
+        let () = Self::ASSERT_PEER;
+        encode(Self::LAYOUT.ext_id_to(dst, node_id, Self::msg_id::<X>(), prio)?, x)
+    }
+
+    /// same as `encode_to`, for any msg of the group
+    pub fn encode_any_to<F: Frame, E: MsgEnum<L>>(dst: NodeId, node_id: NodeId, x: &E) -> Result<F, Error> {
+        let () = Self::ASSERT_PEER;
+        let () = AssertPrio::<L, E, PRIO_LEN>::ENUM;
+        encode_any(Self::LAYOUT.ext_id_to(dst, node_id, x.msg_id(), x.priority())?, x)
+    }
+
+    /// encode with 11 bits standard id, group should be created by `new_std`
+    pub fn encode_std<F: Frame, X: Serialize + Elem<L>>(node_id: NodeId, x: &X) -> Result<F, Error> {
+        Self::encode_std_prio(node_id, Self::prio::<X>(), x)
+    }
+
+    /// same as `encode_std`, with priority `prio` instead of the default one of msg
+    pub fn encode_std_prio<F: Frame, X: Serialize + Elem<L>>(node_id: NodeId, prio: u32, x: &X) -> Result<F, Error> {
+        let () = Self::ASSERT_STD;
+        encode(Self::LAYOUT.std_id(node_id, Self::msg_id::<X>(), prio)?, x)
+    }
+
+    /// mask filter of extended ids passing `sel` with any priority,
+    /// for `Select::Msgs` it may also pass other msgs
+    pub fn mask_filter_ext(sel: Select<'_>) -> Result<MaskFilter, Error> {
+        Self::LAYOUT.mask_filter_ext::<L>(sel)
+    }
+
+    /// same as `mask_filter_ext` for standard ids
+    pub fn mask_filter_std(sel: Select<'_>) -> Result<MaskFilter, Error> {
+        let () = Self::ASSERT_STD;
+        Self::LAYOUT.mask_filter_std::<L>(sel)
+    }
+
+    /// raw ids passing `sel` with priority `prio`, for list filters,
+    /// with dst field, only broadcast ones unless `Select::To`
+    pub fn list_filter(sel: Select<'_>, prio: u32) -> Result<impl Iterator<Item = u32> + '_, Error> {
+        Self::LAYOUT.list_filter::<L>(sel, prio)
+    }
+
+    /// remote frame with extended id requesting msg `X` from node `node_id`,
+    /// DLC is max size of `X`
+    pub fn request<F: Frame, X: Elem<L> + MaxSize>(node_id: NodeId) -> Result<F, Error> {
+        request::<F, X>(Self::LAYOUT.ext_id(node_id, Self::msg_id::<X>(), Self::prio::<X>())?)
+    }
+
+    /// same as `request` with standard id
+    pub fn request_std<F: Frame, X: Elem<L> + MaxSize>(node_id: NodeId) -> Result<F, Error> {
+        let () = Self::ASSERT_STD;
+        request::<F, X>(Self::LAYOUT.std_id(node_id, Self::msg_id::<X>(), Self::prio::<X>())?)
+    }
+
+    /// whether `f` is a remote frame of this group requesting msg `X`,
+    /// the requested node is from `classify`
+    pub fn is_request_for<X: Elem<L>, F: Frame>(f: &F) -> bool {
+        f.is_remote_frame() && matches!(Self::classify(&f.id()), Some((_, m)) if m == Self::msg_id::<X>())
+    }
+
+    /// node_id & msg_id of `id`, `None` if not this node group, payload is not decoded
+    pub fn classify(id: &Id) -> Option<(NodeId, MsgId)> {
+        Self::LAYOUT.classify(id)
+    }
+
+    /// priority of `id`, `None` if not this node group
+    pub fn priority(id: &Id) -> Option<u32> {
+        Self::LAYOUT.priority(id)
+    }
+
+    /// node_id, dst & msg_id of `id`, dst is broadcast without `PEER`
+    pub fn classify_peer(id: &Id) -> Option<(NodeId, NodeId, MsgId)> {
+        Self::LAYOUT.classify_peer(id)
+    }
+
+    /// same as `decode`, also return dst
+    pub fn decode_peer<'a, T: Deserialize<'a> + Elem<L>, F: Frame>(f: &'a F) -> Result<(NodeId, NodeId, T), Error> {
+        let () = Self::ASSERT_PEER;
+        decode_peer(&Self::LAYOUT, Self::msg_id::<T>(), f)
+    }
+
+    /// same as `decode_any`, also return dst
+    pub fn decode_any_peer<E: MsgEnum<L>, F: Frame>(f: &F) -> Result<(NodeId, NodeId, E), Error> {
+        let () = Self::ASSERT_PEER;
+        decode_any_peer(&Self::LAYOUT, f)
+    }
+
+    pub fn decode<'a, T: Deserialize<'a> + Elem<L>, F: Frame>(f: &'a F) -> Result<(NodeId, T), Error> {
+        decode(&Self::LAYOUT, Self::msg_id::<T>(), f)
+    }
+
+    /// decode any msg of the group, dispatch on msg_id
+    pub fn decode_any<E: MsgEnum<L>, F: Frame>(f: &F) -> Result<(NodeId, E), Error> {
+        decode_any(&Self::LAYOUT, f)
+    }
+}
+
+// default priority of msg `X` or of all msgs of enum `X` should fit in `PRIO_LEN` bits,
+// in a type as the check is per msg
+struct AssertPrio<L, X, const PRIO_LEN: usize>(PhantomData<(L, X)>);
+
+impl <L: List, X: Elem<L>, const PRIO_LEN: usize> AssertPrio<L, X, PRIO_LEN> {
+    const ELEM: () = assert!((X::PRIORITY as u64) < (1 << PRIO_LEN),
+                             "default priority of msg should fit within PRIO_LEN bits");
+}
+
+impl <L: List, E: MsgEnum<L>, const PRIO_LEN: usize> AssertPrio<L, E, PRIO_LEN> {
+    const ENUM: () = assert!((E::MAX_PRIORITY as u64) < (1 << PRIO_LEN),
+                             "default priority of msg should fit within PRIO_LEN bits");
+}
+
+/// `NodeGroup` of any const params, for types generic over the group, e.g. `rpc::Client`
+pub trait Group {
+    type Msgs: List;
+
+    fn classify(id: &Id) -> Option<(NodeId, MsgId)>;
+    fn classify_peer(id: &Id) -> Option<(NodeId, NodeId, MsgId)>;
+    fn msg_id<X: Elem<Self::Msgs>>() -> MsgId;
+    fn encode_ext<F: Frame, X: Serialize + Elem<Self::Msgs>>(node_id: NodeId, x: &X) -> Result<F, Error>;
+    fn encode_to<F: Frame, X: Serialize + Elem<Self::Msgs>>(dst: NodeId, node_id: NodeId, x: &X) -> Result<F, Error>;
+    fn decode<'a, T: Deserialize<'a> + Elem<Self::Msgs>, F: Frame>(f: &'a F) -> Result<(NodeId, T), Error>;
+    fn decode_peer<'a, T: Deserialize<'a> + Elem<Self::Msgs>, F: Frame>(f: &'a F)
+                                                                      -> Result<(NodeId, NodeId, T), Error>;
+}
+
+impl <L: List, const BASE: u32, const NODE_ID_LEN: usize, const MSG_ID_LEN: usize, const PRIO_LEN: usize,
+      const PEER: bool>
+    Group for NodeGroup<L, BASE, NODE_ID_LEN, MSG_ID_LEN, PRIO_LEN, PEER>
+{
+    type Msgs = L;
+
+    fn classify(id: &Id) -> Option<(NodeId, MsgId)> { Self::classify(id) }
+    fn classify_peer(id: &Id) -> Option<(NodeId, NodeId, MsgId)> { Self::classify_peer(id) }
+    fn msg_id<X: Elem<L>>() -> MsgId { Self::msg_id::<X>() }
+
+    fn encode_ext<F: Frame, X: Serialize + Elem<L>>(node_id: NodeId, x: &X) -> Result<F, Error> {
+        Self::encode_ext(node_id, x)
+    }
+
+    fn encode_to<F: Frame, X: Serialize + Elem<L>>(dst: NodeId, node_id: NodeId, x: &X) -> Result<F, Error> {
+        Self::encode_to(dst, node_id, x)
+    }
+
+    fn decode<'a, T: Deserialize<'a> + Elem<L>, F: Frame>(f: &'a F) -> Result<(NodeId, T), Error> {
+        Self::decode(f)
+    }
+
+    fn decode_peer<'a, T: Deserialize<'a> + Elem<L>, F: Frame>(f: &'a F) -> Result<(NodeId, NodeId, T), Error> {
+        Self::decode_peer(f)
+    }
+}
+
+// ---------------------------------- dyn node group
+/// same as `NodeGroup`, with layout chosen at runtime, e.g. `BASE` from EEPROM
+#[derive(Debug)]
+pub struct DynNodeGroup<L: List> {
+    pub name: &'static str,
+    layout: Layout,
+    _phantom: PhantomData<L>,
+}
+
+impl <L: List> DynNodeGroup<L> {
+    /// error if `layout` is invalid, e.g. `LAYOUT` of a `NodeGroup` not checked, or msg_id of `L` not fit in it
+    pub fn new(name: &'static str, layout: Layout) -> Result<Self, Error> {
+        let layout = layout.check(29)?;
+        if L::END as u64 > (1 << layout.msg_id_len) {
+            return Err(Error::LayoutMsgIdOverflow(L::END - 1, layout.msg_id_len))
         }
+
+        Ok(DynNodeGroup { name, layout, _phantom: PhantomData })
+    }
+
+    pub fn layout(&self) -> &Layout { &self.layout }
+
+    pub fn msg_id<X: Elem<L>>() -> MsgId { MsgId(X::MSG_ID) }
+
+    // check on every call, as std ids are not checked by `Layout::new`
+    fn std_layout(&self) -> Result<Layout, Error> {
+        self.layout.check(11)
+    }
+
+    pub fn encode_ext<F: Frame, X: Serialize + Elem<L>>(&self, node_id: NodeId, x: &X) -> Result<F, Error> {
+        self.encode_ext_prio(node_id, X::PRIORITY, x)
+    }
+
+    pub fn encode_ext_prio<F: Frame, X: Serialize + Elem<L>>(&self, node_id: NodeId, prio: u32, x: &X) -> Result<F, Error> {
+        encode(self.layout.ext_id(node_id, Self::msg_id::<X>(), prio)?, x)
+    }
+
+    pub fn encode_any<F: Frame, E: MsgEnum<L>>(&self, node_id: NodeId, x: &E) -> Result<F, Error> {
+        encode_any(self.layout.ext_id(node_id, x.msg_id(), x.priority())?, x)
+    }
+
+    pub fn encode_std<F: Frame, X: Serialize + Elem<L>>(&self, node_id: NodeId, x: &X) -> Result<F, Error> {
+        self.encode_std_prio(node_id, X::PRIORITY, x)
+    }
+
+    fn peer_layout(&self) -> Result<&Layout, Error> {
+        if self.layout.peer { Ok(&self.layout) } else { Err(Error::LayoutNoDst) }
+    }
+
+    pub fn encode_to<F: Frame, X: Serialize + Elem<L>>(&self, dst: NodeId, node_id: NodeId, x: &X) -> Result<F, Error> {
+        self.encode_to_prio(dst, node_id, X::PRIORITY, x)
     }
 
+    pub fn encode_to_prio<F: Frame, X: Serialize + Elem<L>>(&self, dst: NodeId, node_id: NodeId, prio: u32, x: &X)
+                                                            -> Result<F, Error> {
+        encode(self.peer_layout()?.ext_id_to(dst, node_id, Self::msg_id::<X>(), prio)?, x)
+    }
+
+    pub fn encode_any_to<F: Frame, E: MsgEnum<L>>(&self, dst: NodeId, node_id: NodeId, x: &E) -> Result<F, Error> {
+        encode_any(self.peer_layout()?.ext_id_to(dst, node_id, x.msg_id(), x.priority())?, x)
+    }
+
+    pub fn encode_std_prio<F: Frame, X: Serialize + Elem<L>>(&self, node_id: NodeId, prio: u32, x: &X) -> Result<F, Error> {
+        encode(self.std_layout()?.std_id(node_id, Self::msg_id::<X>(), prio)?, x)
+    }
+
+    pub fn mask_filter_ext(&self, sel: Select<'_>) -> Result<MaskFilter, Error> {
+        self.layout.mask_filter_ext::<L>(sel)
+    }
+
+    pub fn mask_filter_std(&self, sel: Select<'_>) -> Result<MaskFilter, Error> {
+        self.std_layout()?.mask_filter_std::<L>(sel)
+    }
+
+    pub fn list_filter<'a>(&self, sel: Select<'a>, prio: u32) -> Result<impl Iterator<Item = u32> + 'a, Error> {
+        self.layout.list_filter::<L>(sel, prio)
+    }
+
+    pub fn request<F: Frame, X: Elem<L> + MaxSize>(&self, node_id: NodeId) -> Result<F, Error> {
+        request::<F, X>(self.layout.ext_id(node_id, Self::msg_id::<X>(), X::PRIORITY)?)
+    }
+
+    pub fn request_std<F: Frame, X: Elem<L> + MaxSize>(&self, node_id: NodeId) -> Result<F, Error> {
+        request::<F, X>(self.std_layout()?.std_id(node_id, Self::msg_id::<X>(), X::PRIORITY)?)
+    }
+
+    pub fn is_request_for<X: Elem<L>, F: Frame>(&self, f: &F) -> bool {
+        f.is_remote_frame() && matches!(self.classify(&f.id()), Some((_, m)) if m == Self::msg_id::<X>())
+    }
+
+    pub fn classify(&self, id: &Id) -> Option<(NodeId, MsgId)> {
+        self.layout.classify(id)
+    }
+
+    pub fn priority(&self, id: &Id) -> Option<u32> {
+        self.layout.priority(id)
+    }
+
+    pub fn classify_peer(&self, id: &Id) -> Option<(NodeId, NodeId, MsgId)> {
+        self.layout.classify_peer(id)
+    }
+
+    pub fn decode_peer<'a, T: Deserialize<'a> + Elem<L>, F: Frame>(&self, f: &'a F) -> Result<(NodeId, NodeId, T), Error> {
+        decode_peer(&self.layout, Self::msg_id::<T>(), f)
+    }
+
+    pub fn decode_any_peer<E: MsgEnum<L>, F: Frame>(&self, f: &F) -> Result<(NodeId, NodeId, E), Error> {
+        decode_any_peer(&self.layout, f)
+    }
+
+    pub fn decode<'a, T: Deserialize<'a> + Elem<L>, F: Frame>(&self, f: &'a F) -> Result<(NodeId, T), Error> {
+        decode(&self.layout, Self::msg_id::<T>(), f)
+    }
+
+    pub fn decode_any<E: MsgEnum<L>, F: Frame>(&self, f: &F) -> Result<(NodeId, E), Error> {
+        decode_any(&self.layout, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+
+    use crate::CanFrame as Frame;
+
     node_group_msg_def!(T4, [isize, u8, i8, usize]);
     node_group_msg_def!(T5, [u32, isize, u8, i8, usize,]);
 
@@ -255,11 +1109,315 @@ mod test {
         assert_eq!(T4::msg_id::<i8>(),    2);
         assert_eq!(T4::msg_id::<u8>(),    1);
         assert_eq!(T4::msg_id::<isize>(), 0);
-        //assert_eq!(T4::msg_id::<u32>(),  -1);
+        // T4::msg_id::<u32>() not compile, u32 is not in T4
 
         assert_eq!(T5::msg_id::<usize>(), 4);
         assert_eq!(T5::msg_id::<u32>(),   0);
-        //assert_eq!(T5::msg_id::<i32>(),  -1);
+    }
+
+    #[test]
+    fn classify() {
+        type G0 = NodeGroup::<T5, 0x1_9876_540, 3, 3>;
+
+        let f: Frame = G0::encode_ext(5, &-1i8).unwrap();
+        let id = embedded_can::Frame::id(&f);
+        assert_eq!(G0::classify(&id), Some((5, G0::msg_id::<i8>())));
+        assert_eq!(NodeGroup::<T5, 0x1_9876_500, 3, 3>::classify(&id), None);
+    }
+
+    #[derive(Serialize)]
+    struct Tag<'a>(&'a str);
+    node_group_msg_def!(T2, [Tag<'_>, u64]);
+
+    #[test]
+    fn borrowed() {
+        type G0 = NodeGroup::<T2, 0x1_9876_540, 3, 3>;
+        const { assert!(G0::msg_id::<Tag>().0 == 0) };
+
+        let name = std::string::String::from("ab");
+        let f: Frame = G0::encode_ext(1, &Tag(&name)).unwrap();
+        assert_eq!(G0::classify(&embedded_can::Frame::id(&f)), Some((1, MsgId(0))));
+
+        let f: Frame = G0::encode_ext(1, &7u64).unwrap();
+        assert_eq!(G0::decode::<u64, _>(&f), Ok((1, 7)));
+    }
+
+    node_group_msg_def!(T6, [i64 = 0, u16 = 3, char = 7,], reserved [1, 2]);
+    node_group_msg_def!(T7, #[derive(Debug, PartialEq)] enum Cmd { Stop(u8) = 2, Go(i8) = 5 });
+
+    #[test]
+    fn explicit_id() {
+        type G0 = NodeGroup::<T6, 0x1_9876_540, 3, 3>;
+        let _g = G0::new("g");
+
+        assert_eq!((T6::LEN, T6::END), (3, 8));
+        assert_eq!((T12::LEN, T12::END), (2, 5));
+        assert_eq!(G0::msg_id::<u16>(), MsgId(3));
+        assert_eq!(G0::msg_id::<char>(), MsgId(7));
+
+        let f: Frame = G0::encode_ext(1, &'x').unwrap();
+        assert_eq!(G0::classify(&embedded_can::Frame::id(&f)), Some((1, MsgId(7))));
+        assert_eq!(G0::decode::<char, _>(&f), Ok((1, 'x')));
+
+        type G1 = NodeGroup::<T7, 0x1_9876_540, 3, 3>;
+        let f: Frame = G1::encode_any(1, &Cmd::Go(-1)).unwrap();
+        assert_eq!(G1::classify(&embedded_can::Frame::id(&f)), Some((1, MsgId(5))));
+        assert_eq!(G1::decode_any::<Cmd, _>(&f), Ok((1, Cmd::Go(-1))));
+    }
+
+    #[test]
+    #[should_panic]
+    fn assert_explicit_id() {
+        let _g = NodeGroup::<T6, 0x0, 3, 2>::new("g");
+    }
+
+    node_group_msg_def!(T12, [u8 = 0, i8 = 1], reserved [4]);
+
+    #[test]
+    #[should_panic]
+    fn assert_reserved_id() {
+        let _g = NodeGroup::<T12, 0x0, 3, 2>::new("g");
+    }
+
+    node_group_msg_def!(T8, #[derive(Debug, PartialEq)] enum Pri { #[prio = 2] Lo(i32), Hi(f32) });
+
+    #[test]
+    fn priority() {
+        use embedded_can::Frame as _;
+        type G0 = NodeGroup::<T8, 0x1_9876_500, 3, 3, 2>;
+        let _g = G0::new("g");
+
+        let f: Frame = G0::encode_any(1, &Pri::Lo(5)).unwrap();
+        assert_eq!(f.id(), Id::Extended(ExtendedId::new(0x1_9876_588).unwrap()));
+        assert_eq!(G0::priority(&f.id()), Some(2));
+        assert_eq!(G0::classify(&f.id()), Some((1, MsgId(0))));
+        assert_eq!(G0::decode_any::<Pri, _>(&f), Ok((1, Pri::Lo(5))));
+
+        let f: Frame = G0::encode_ext(1, &0.5f32).unwrap();
+        assert_eq!(G0::priority(&f.id()), Some(0));
+
+        let f: Frame = G0::encode_ext_prio(1, 3, &5i32).unwrap();
+        assert_eq!(G0::priority(&f.id()), Some(3));
+        assert_eq!(G0::decode::<i32, _>(&f), Ok((1, 5)));
+
+        assert_eq!(G0::encode_ext_prio::<Frame, _>(1, 4, &5i32), Err(Error::EncPriorityOutOfRange(4, 2)));
+        // default priority of `Lo` does not compile without prio bits, other msgs do
+        assert!(NodeGroup::<T8, 0x1_9876_500, 3, 3>::encode_ext::<Frame, _>(1, &0.5f32).is_ok());
+        assert_eq!(<Pri as MsgEnum<T8>>::MAX_PRIORITY, 2);
+    }
+
+    node_group_msg_def!(T9, [u8 = 0, i16 = 1, u32 = 2, i64 = 5]);
+
+    #[test]
+    fn filter() {
+        use embedded_can::Frame as _;
+        type G0 = NodeGroup::<T9, 0x1_9876_500, 3, 3, 2>;
+        let _g = G0::new("g");
+
+        let f = G0::mask_filter_ext(Select::Group).unwrap();
+        assert_eq!((f.id, f.mask), (0x1_9876_500, 0x1fff_ff00));
+        assert_eq!(f.range(), 0x1_9876_500..=0x1_9876_5ff);
+
+        let f = G0::mask_filter_ext(Select::Node(2)).unwrap();
+        assert_eq!((f.id, f.mask), (0x1_9876_510, 0x1fff_ff38));
+        let x: Frame = G0::encode_ext_prio(2, 3, &5i64).unwrap();
+        let y: Frame = G0::encode_ext(3, &5i64).unwrap();
+        assert!(f.matches(&x.id()) && !f.matches(&y.id()));
+        assert_eq!(f.range(), 0x1_9876_510..=0x1_9876_5d7);
+
+        let f = G0::mask_filter_ext(Select::Msg(G0::msg_id::<u32>())).unwrap();
+        assert_eq!((f.id, f.mask), (0x1_9876_502, 0x1fff_ff07));
+
+        let f = G0::mask_filter_ext(Select::Msgs(&[MsgId(1), MsgId(5)])).unwrap();
+        assert_eq!((f.id, f.mask), (0x1_9876_501, 0x1fff_ff03));
+        let z: Frame = G0::encode_ext(2, &5u32).unwrap();
+        assert!(f.matches(&x.id()) && !f.matches(&z.id()));
+
+        assert_eq!(G0::mask_filter_ext(Select::Node(8)), Err(Error::EncNodeIdOutOfRange(8, 3)));
+        assert_eq!(G0::mask_filter_ext(Select::Msg(MsgId(3))), Err(Error::DecUnknownMsgId(3)));
+        assert_eq!(G0::mask_filter_ext(Select::Msgs(&[])), Err(Error::SelectEmpty));
+        assert!(G0::list_filter(Select::Msgs(&[]), 0).is_err());
+
+        let ids = |sel, prio| G0::list_filter(sel, prio).unwrap().collect::<std::vec::Vec<_>>();
+        assert_eq!(ids(Select::NodeMsg(1, MsgId(5)), 1), [0x1_9876_54d]);
+        assert_eq!(ids(Select::Node(0), 0), [0x1_9876_500, 0x1_9876_501, 0x1_9876_502, 0x1_9876_505]);
+        assert_eq!(ids(Select::Group, 0).len(), 32);
+        assert_eq!(ids(Select::Msgs(&[MsgId(1), MsgId(5)]), 0).len(), 16);
+        assert!(G0::list_filter(Select::Group, 4).is_err());
+
+        type G1 = NodeGroup::<T9, 0x400, 3, 3>;
+        let f = G1::mask_filter_std(Select::Group).unwrap();
+        assert_eq!(f, MaskFilter { id: 0x400, mask: 0x7c0, ext: false });
+        assert!(f.matches(&Id::Standard(StandardId::new(0x42b).unwrap())));
+        assert!(!f.matches(&Id::Extended(ExtendedId::new(0x42b).unwrap())));
+        assert_eq!(f.range(), 0x400..=0x43f);
+    }
+
+    #[test]
+    fn request() {
+        use embedded_can::Frame as _;
+        type G0 = NodeGroup::<T9, 0x1_9876_500, 3, 3>;
+        let _g = G0::new("g");
+
+        let f: Frame = G0::request::<_, i16>(4).unwrap();
+        assert!(f.is_remote_frame());
+        assert_eq!((f.id(), f.dlc()), (Id::Extended(ExtendedId::new(0x1_9876_521).unwrap()), 2));
+        assert!(G0::is_request_for::<i16, _>(&f) && !G0::is_request_for::<u8, _>(&f));
+        assert_eq!(G0::classify(&f.id()), Some((4, MsgId(1))));
+        assert_eq!(G0::decode::<i16, _>(&f), Err(Error::FrameErr(crate::frame::Error::RemoteFrame)));
+
+        // response is not a request
+        let f: Frame = G0::encode_ext(4, &7i16).unwrap();
+        assert!(!G0::is_request_for::<i16, _>(&f));
+
+        type G1 = NodeGroup::<T9, 0x400, 3, 3>;
+        let f: Frame = G1::request_std::<_, u32>(1).unwrap();
+        assert_eq!((f.id(), f.dlc()), (Id::Standard(StandardId::new(0x40a).unwrap()), 4));
+        assert!(G1::is_request_for::<u32, _>(&f));
+        assert_eq!(G1::request::<Frame, u8>(8), Err(Error::EncNodeIdOutOfRange(8, 3)));
+    }
+
+    node_group_msg_def!(T10, #[derive(Debug, PartialEq)] enum Dm { A(u8), B(bool), C(i16) });
+
+    #[test]
+    fn layout() {
+        assert_eq!(Layout::new(0x1_9876_500, 3, 3, 2).map(|x| x.base_mask()), Ok(0x1fff_ff00 | 0xe000_0000));
+        assert_eq!(Layout::new(0x1_9876_540, 3, 3, 2), Err(Error::LayoutBaseOverlap(0x1_9876_540)));
+        assert_eq!(Layout::new(0x2000_0000, 3, 3, 0), Err(Error::LayoutBaseOutOfRange(0x2000_0000)));
+        assert_eq!(Layout::new(0, 15, 15, 0), Err(Error::LayoutTooLong(30, 29)));
+        assert_eq!(Layout::new(0, usize::MAX, 1, 0), Err(Error::LayoutTooLong(usize::MAX, 29)));
+        assert_eq!(Layout::new_std(0x800, 3, 3, 0), Err(Error::LayoutBaseOutOfRange(0x800)));
+        assert_eq!(NodeGroup::<T10, 0x1_9876_500, 3, 3, 2>::LAYOUT, Layout::new(0x1_9876_500, 3, 3, 2).unwrap());
+
+        let layout = Layout::new(0, 3, 1, 0).unwrap();
+        assert!(matches!(DynNodeGroup::<T10>::new("g", layout), Err(Error::LayoutMsgIdOverflow(2, 1))));
+        let layout = NodeGroup::<T10, 0, 20, 12>::LAYOUT;
+        assert!(matches!(DynNodeGroup::<T10>::new("g", layout), Err(Error::LayoutTooLong(32, 29))));
+        let layout = NodeGroup::<T10, 0x1_9876_520, 3, 3>::LAYOUT;
+        assert!(matches!(DynNodeGroup::<T10>::new("g", layout), Err(Error::LayoutBaseOverlap(0x1_9876_520))));
+    }
+
+    #[test]
+    fn dyn_group() {
+        use embedded_can::Frame as _;
+        type G0 = NodeGroup::<T10, 0x1_9876_500, 3, 3, 2>;
+
+        // same ids as const group
+        let g = DynNodeGroup::<T10>::new("g", Layout::new(0x1_9876_500, 3, 3, 2).unwrap()).unwrap();
+        let f: Frame = g.encode_ext(2, &-5i16).unwrap();
+        assert_eq!(f, G0::encode_ext::<Frame, _>(2, &-5i16).unwrap());
+        assert_eq!(g.classify(&f.id()), Some((2, MsgId(2))));
+        assert_eq!(g.decode::<i16, _>(&f), Ok((2, -5)));
+        assert_eq!(g.decode::<u8, _>(&f), Err(Error::DecMsgIdMismatch(2, 0)));
+        assert_eq!(g.decode_any::<Dm, _>(&f), Ok((2, Dm::C(-5))));
+
+        let f: Frame = g.encode_any(1, &Dm::B(true)).unwrap();
+        assert_eq!(g.priority(&f.id()), Some(0));
+        let f: Frame = g.encode_ext_prio(1, 3, &true).unwrap();
+        assert_eq!(g.priority(&f.id()), Some(3));
+        assert_eq!(g.encode_ext::<Frame, _>(8, &true), Err(Error::EncNodeIdOutOfRange(8, 3)));
+
+        assert_eq!(g.mask_filter_ext(Select::Node(2)), G0::mask_filter_ext(Select::Node(2)));
+        assert_eq!(g.list_filter(Select::Group, 1).unwrap().count(), 24);
+        let f: Frame = g.request::<_, i16>(4).unwrap();
+        assert!(g.is_request_for::<i16, _>(&f) && f.dlc() == 2);
+
+        // other base at runtime
+        let h = DynNodeGroup::<T10>::new("h", Layout::new(0x1_9876_600, 3, 3, 2).unwrap()).unwrap();
+        let f: Frame = h.encode_ext(2, &-5i16).unwrap();
+        assert_eq!(g.decode::<i16, _>(&f), Err(Error::DecNodeGroupMismatch));
+        assert_eq!(h.decode::<i16, _>(&f), Ok((2, -5)));
+
+        // standard ids
+        assert_eq!(g.encode_std::<Frame, _>(2, &1u8), Err(Error::LayoutBaseOutOfRange(0x1_9876_500)));
+        let s = DynNodeGroup::<T10>::new("s", Layout::new_std(0x400, 3, 3, 0).unwrap()).unwrap();
+        let f: Frame = s.encode_std(5, &1u8).unwrap();
+        assert_eq!(f.id(), Id::Standard(StandardId::new(0x428).unwrap()));
+        assert_eq!(s.mask_filter_std(Select::Group), Ok(MaskFilter { id: 0x400, mask: 0x7c0, ext: false }));
+        assert!(s.request_std::<Frame, u8>(5).unwrap().is_remote_frame());
+    }
+
+    #[test]
+    fn id_space() {
+        use std::string::ToString;
+        const SPACES: [IdSpace; 3] = [
+            IdSpace::group("t4", NodeGroup::<T4, 0x1_1234_560, 3, 3>::LAYOUT),
+            IdSpace::group("t5", NodeGroup::<T5, 0x1_9876_540, 3, 3>::LAYOUT),
+            IdSpace::fixed_std("sync", 0x80),
+        ];
+        const _: () = check_id_spaces(&SPACES);
+        assert_eq!(find_overlap(&SPACES), None);
+
+        // same bits of other id kind
+        assert!(!IdSpace::fixed_std("a", 0x541).overlaps(&IdSpace::fixed("b", 0x541)));
+        assert!(IdSpace::group_std("s", NodeGroup::<T10, 0x400, 3, 3>::LAYOUT).overlaps(&IdSpace::fixed_std("b", 0x42b)));
+        assert!(!IdSpace::group_std("s", NodeGroup::<T10, 0x400, 3, 3>::LAYOUT).overlaps(&IdSpace::fixed("b", 0x42b)));
+
+        let spaces = [
+            IdSpace::group("a", NodeGroup::<T10, 0x1_9876_500, 3, 3, 2>::LAYOUT),
+            SPACES[1],
+            IdSpace::fixed("sync", 0x1_9876_5ff),
+            SPACES[0],
+        ];
+        assert_eq!(find_overlap(&spaces), Some((0, 1)));
+
+        let res: std::vec::Vec<_> = collisions(&spaces).collect();
+        assert_eq!(res.len(), 2);
+        assert_eq!((res[0].b.name, res[0].example()), ("t5", 0x1_9876_540));
+        assert_eq!(res[1].to_string(),
+                   "`a` (id 0x19876500, mask 0x1fffff00) overlaps `sync` (id 0x198765ff, mask 0x1fffffff), e.g. id 0x198765ff");
+    }
+
+    #[test]
+    #[should_panic(expected = "id space `t5` overlaps `sync`")]
+    fn assert_id_space() {
+        check_id_spaces(&[IdSpace::group("t5", NodeGroup::<T5, 0x1_9876_540, 3, 3>::LAYOUT),
+                          IdSpace::fixed("sync", 0x1_9876_541)]);
+    }
+
+    node_group_msg_def!(T11, #[derive(Debug, PartialEq)] enum Pm { Cmd(u8), Ack(bool) });
+
+    #[test]
+    fn peer() {
+        use embedded_can::Frame as _;
+        type G0 = NodeGroup::<T11, 0x1_9870_000, 4, 2, 0, true>;
+        let _g = G0::new("g");
+        assert_eq!((G0::BROADCAST, G0::LAYOUT), (15, Layout::new_peer(0x1_9870_000, 4, 2, 0).unwrap()));
+
+        let f: Frame = G0::encode_to(3, 5, &7u8).unwrap();
+        assert_eq!(f.id(), Id::Extended(ExtendedId::new(0x1_9870_0d4).unwrap()));
+        assert_eq!(G0::decode_peer::<u8, _>(&f), Ok((5, 3, 7)));
+        assert_eq!(G0::decode::<u8, _>(&f), Ok((5, 7)));
+
+        // broadcast without dst
+        let g: Frame = G0::encode_ext(5, &7u8).unwrap();
+        assert_eq!(G0::classify_peer(&g.id()), Some((5, 15, MsgId(0))));
+
+        let h: Frame = G0::encode_any_to(2, 1, &Pm::Ack(true)).unwrap();
+        assert_eq!(G0::decode_any_peer::<Pm, _>(&h), Ok((1, 2, Pm::Ack(true))));
+
+        // msgs to node 3
+        let to = G0::mask_filter_ext(Select::To(3)).unwrap();
+        assert_eq!((to.id, to.mask), (0x1_9870_0c0, 0x1fff_ffc0));
+        assert!(to.matches(&f.id()) && !to.matches(&g.id()) && !to.matches(&h.id()));
+        assert!(G0::mask_filter_ext(Select::To(G0::BROADCAST)).unwrap().matches(&g.id()));
+        assert_eq!(G0::list_filter(Select::To(3), 0).unwrap().count(), 32);
+
+        // msg from node 5, to any dst
+        let from = G0::mask_filter_ext(Select::NodeMsg(5, MsgId(0))).unwrap();
+        assert_eq!((from.id, from.mask), (0x1_9870_014, 0x1fff_fc3f));
+        assert!(from.matches(&f.id()) && from.matches(&g.id()) && !from.matches(&h.id()));
+
+        assert_eq!(G0::encode_to::<Frame, _>(16, 5, &7u8), Err(Error::EncNodeIdOutOfRange(16, 4)));
+        assert_eq!(NodeGroup::<T11, 0x1_9870_000, 4, 2>::mask_filter_ext(Select::To(1)), Err(Error::LayoutNoDst));
+        assert_eq!(Layout::new_peer(0, 15, 1, 0), Err(Error::LayoutTooLong(31, 29)));
+
+        let d = DynNodeGroup::<T11>::new("d", G0::LAYOUT).unwrap();
+        assert_eq!(d.encode_to::<Frame, _>(3, 5, &7u8), Ok(f));
+        assert_eq!(d.decode_peer::<u8, _>(&f), Ok((5, 3, 7)));
+        let d = DynNodeGroup::<T11>::new("d", Layout::new(0x1_9870_000, 4, 2, 0).unwrap()).unwrap();
+        assert_eq!(d.encode_to::<Frame, _>(3, 5, &7u8), Err(Error::LayoutNoDst));
+        assert_eq!(d.decode_peer::<u8, _>(&f), Err(Error::LayoutNoDst));
     }
 
     #[test]
@@ -281,6 +1439,39 @@ mod test {
         }
     }
 
+    #[test]
+    fn endec_std() {
+        use embedded_can::Frame as _;
+        type G0 = NodeGroup::<T5, 0x400, 3, 3>;
+        let _g = G0::new_std("g");
+
+        let f: Frame = G0::encode_std(5, &-123i8).unwrap();
+        assert_eq!(f.id(), Id::Standard(StandardId::new(0x42b).unwrap()));
+        assert_eq!(G0::decode::<i8, _>(&f), Ok((5, -123)));
+        assert_eq!(G0::encode_std::<Frame, _>(8, &0u8), Err(Error::EncNodeIdOutOfRange(8, 3)));
+    }
+
+    node_group_msg_def!(T3, #[derive(Debug, PartialEq)] enum Msg { A(bool), B(i16), C(u32) });
+
+    #[test]
+    fn endec_any() {
+        type G0 = NodeGroup::<T3, 0x1_9876_540, 3, 3>;
+
+        let f: Frame = G0::encode_any(2, &Msg::B(-2)).unwrap();
+        assert_eq!(G0::decode::<i16, _>(&f), Ok((2, -2)));
+        assert_eq!(G0::decode_any::<Msg, _>(&f), Ok((2, Msg::B(-2))));
+
+        let f: Frame = G0::encode_ext(3, &7u32).unwrap();
+        assert_eq!(G0::decode_any::<Msg, _>(&f), Ok((3, Msg::C(7))));
+
+        type G1 = NodeGroup::<T3, 0x1_9876_500, 3, 3>;
+        let f: Frame = G1::encode_ext(3, &7u32).unwrap();
+        assert_eq!(G0::decode_any::<Msg, _>(&f), Err(Error::DecNodeGroupMismatch));
+
+        let f: Frame = embedded_can::Frame::new(ExtendedId::new(0x1_9876_55d).unwrap(), &[]).unwrap();
+        assert_eq!(G0::decode_any::<Msg, _>(&f), Err(Error::DecUnknownMsgId(5)));
+    }
+
     #[test]
     fn err() {
         type G0 = NodeGroup::<T5, 0x1_9876_540, 3, 3>;
@@ -290,6 +1481,12 @@ mod test {
         assert_eq!(G1::encode_ext::<Frame, _>(8, &0u8), Err(Error::EncNodeIdOutOfRange(8, 3)));
         assert_eq!(G2::encode_ext::<Frame, _>(0, &0u8), Err(Error::EncCanIdOutOfRange(0x2_0000_001)));
 
+        // hand-written `Elem` with a msg_id not fitting the layout
+        #[derive(Serialize)]
+        struct Big;
+        impl Elem<T4> for Big { const MSG_ID: u32 = 8; }
+        assert_eq!(G1::encode_ext::<Frame, _>(0, &Big), Err(Error::EncMsgIdOutOfRange(8, 3)));
+
         let Ok(f) = G0::encode_ext::<Frame, _>(5, &-123i8) else { panic!("fail") };
 
         assert_eq!(G0::decode::<u8, _>(&f), Err(Error::DecMsgIdMismatch(3, 2)));
@@ -0,0 +1,278 @@
+//! typed request/response over a `NodeGroup` with dst field (`PEER`), sans-IO
+//!
+//! A request is sent from node_id of the caller to dst of the called node, the
+//! response comes back with the two swapped, so calls of different callers
+//! have different ids. Pending calls are matched by called node & msg_id of
+//! the response, and by a sequence number in payload if `Request::seq` is given.
+
+use core::marker::PhantomData;
+
+use embedded_can::Frame;
+use heapless::Vec;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::node_group::{self, Elem, Group, List, NodeId};
+use crate::Clock;
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("call to node {0} in progress or too many calls")]
+    Busy(NodeId),
+    #[error("call to node {0} timeout")]
+    Timeout(NodeId),
+    #[error("node group error: {0}")]
+    NodeGroupErr(node_group::Error),
+}
+
+impl From<node_group::Error> for Error {
+    fn from(e: node_group::Error) -> Self { Error::NodeGroupErr(e) }
+}
+
+/// request msg of group `L` & its response
+pub trait Request<L: List>: Serialize + Elem<L> {
+    type Response: Serialize + DeserializeOwned + Elem<L>;
+
+    /// sequence number in payload, `None` if not used
+    fn seq(&self) -> Option<u8> { None }
+    fn response_seq(_resp: &Self::Response) -> Option<u8> { None }
+}
+
+#[derive(Debug)]
+struct Pending {
+    dst: NodeId,
+    seq: Option<u8>,
+    deadline: u64,
+}
+
+/// caller of request `R` over group `G`, up to `N` calls in progress
+pub struct Client<G, R, C, const N: usize = 4> {
+    clock: C,
+    node_id: NodeId,
+    timeout: u64,
+    pending: Vec<Pending, N>,
+    _phantom: PhantomData<(G, fn(R))>,
+}
+
+impl <G: Group, R: Request<G::Msgs>, C: Clock, const N: usize> Client<G, R, C, N> {
+    /// own node `node_id`, `timeout` in microseconds
+    pub fn new(clock: C, node_id: NodeId, timeout: u64) -> Self {
+        Client { clock, node_id, timeout, pending: Vec::new(), _phantom: PhantomData }
+    }
+
+    pub fn is_pending(&self, dst: NodeId) -> bool {
+        self.pending.iter().any(|x| x.dst == dst)
+    }
+
+    /// frame of the request to node `dst`, one call per node & seq at a time
+    pub fn call<F: Frame>(&mut self, dst: NodeId, req: &R) -> Result<F, Error> {
+        let seq = req.seq();
+        if self.pending.is_full() || self.pending.iter().any(|x| x.dst == dst && x.seq == seq) {
+            return Err(Error::Busy(dst))
+        }
+
+        let f = G::encode_to(dst, self.node_id, req)?;
+        let deadline = self.clock.now() + self.timeout;
+        let _ = self.pending.push(Pending { dst, seq, deadline });
+        Ok(f)
+    }
+
+    /// response of a pending call, `None` for other frames, e.g. responses to other callers
+    pub fn on_frame<F: Frame>(&mut self, f: &F) -> Result<Option<(NodeId, R::Response)>, Error> {
+        let Some((src, dst, msg_id)) = G::classify_peer(&f.id()) else { return Ok(None) };
+        if f.is_remote_frame() || dst != self.node_id || msg_id != G::msg_id::<R::Response>() {
+            return Ok(None)
+        }
+        if !self.is_pending(src) { return Ok(None) }
+
+        let (_, _, resp) = G::decode_peer::<R::Response, F>(f)?;
+        let seq = R::response_seq(&resp);
+        let Some(i) = self.pending.iter().position(|x| x.dst == src && x.seq == seq) else {
+            // late or unsolicited
+            return Ok(None)
+        };
+
+        self.pending.swap_remove(i);
+        Ok(Some((src, resp)))
+    }
+
+    /// drop one expired call, and report it
+    pub fn poll(&mut self) -> Result<(), Error> {
+        let now = self.clock.now();
+        match self.pending.iter().position(|x| now >= x.deadline) {
+            Some(i) => Err(Error::Timeout(self.pending.swap_remove(i).dst)),
+            None => Ok(()),
+        }
+    }
+
+    /// time of next timeout
+    pub fn poll_at(&self) -> Option<u64> {
+        self.pending.iter().map(|x| x.deadline).min()
+    }
+}
+
+/// callee of request `R` over group `G`
+pub struct Server<G, R> {
+    _phantom: PhantomData<(G, fn(R))>,
+}
+
+impl <G: Group, R: Request<G::Msgs> + DeserializeOwned> Server<G, R> {
+    /// request to node `node_id` & its caller, `None` for other frames
+    pub fn on_frame<F: Frame>(node_id: NodeId, f: &F) -> Result<Option<(NodeId, R)>, Error> {
+        match G::classify_peer(&f.id()) {
+            Some((_, dst, m)) if dst == node_id && !f.is_remote_frame() && m == G::msg_id::<R>() => {},
+            _ => return Ok(None),
+        }
+
+        let (src, _, req) = G::decode_peer::<R, F>(f)?;
+        Ok(Some((src, req)))
+    }
+
+    /// frame of response from node `node_id` to its caller `dst`
+    pub fn respond<F: Frame>(node_id: NodeId, dst: NodeId, resp: &R::Response) -> Result<F, Error> {
+        Ok(G::encode_to(dst, node_id, resp)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+    use core::cell::Cell;
+    use std::collections::VecDeque;
+    use serde::Deserialize;
+
+    use crate::CanFrame;
+    use crate::node_group::{Cons, Nil, NodeGroup};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct GetParam { seq: u8, idx: u8 }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Param { seq: u8, value: i32 }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ping;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Pong(u16);
+
+    crate::node_group_msg_def!(T, [GetParam, Param, Ping, Pong]);
+    type G = NodeGroup<T, 0x1_9876_000, 3, 3, 0, true>;
+
+    impl Request<T> for GetParam {
+        type Response = Param;
+        fn seq(&self) -> Option<u8> { Some(self.seq) }
+        fn response_seq(resp: &Param) -> Option<u8> { Some(resp.seq) }
+    }
+
+    impl Request<T> for Ping {
+        type Response = Pong;
+    }
+
+    // node 2 holds params `idx * 10`
+    fn serve(bus: &mut VecDeque<CanFrame>) {
+        let mut out = VecDeque::new();
+        while let Some(f) = bus.pop_front() {
+            if let Some((src, req)) = Server::<G, GetParam>::on_frame(2, &f).unwrap() {
+                out.push_back(Server::<G, GetParam>::respond(2, src, &Param { seq: req.seq, value: req.idx as i32 * 10 }).unwrap());
+            }
+            if let Some((src, Ping)) = Server::<G, Ping>::on_frame(2, &f).unwrap() {
+                out.push_back(Server::<G, Ping>::respond(2, src, &Pong(0x55aa)).unwrap());
+            }
+        }
+        *bus = out;
+    }
+
+    #[test]
+    fn call() {
+        let t = Cell::new(0);
+        let mut c = Client::<G, GetParam, _>::new(|| t.get(), 1, 1000);
+        let mut bus = VecDeque::new();
+
+        bus.push_back(c.call(2, &GetParam { seq: 0, idx: 1 }).unwrap());
+        bus.push_back(c.call(2, &GetParam { seq: 1, idx: 4 }).unwrap());
+        assert_eq!(c.call::<CanFrame>(2, &GetParam { seq: 1, idx: 5 }), Err(Error::Busy(2)));
+        assert!(c.is_pending(2) && !c.is_pending(3));
+
+        serve(&mut bus);
+        assert_eq!(bus.len(), 2);
+
+        // responses in reverse order
+        let r1 = bus.pop_back().unwrap();
+        let r0 = bus.pop_back().unwrap();
+        assert_eq!(c.on_frame(&r1), Ok(Some((2, Param { seq: 1, value: 40 }))));
+        assert_eq!(c.on_frame(&r0), Ok(Some((2, Param { seq: 0, value: 10 }))));
+        assert!(!c.is_pending(2));
+
+        // duplicate response
+        assert_eq!(c.on_frame(&r0), Ok(None));
+        assert_eq!(c.poll_at(), None);
+    }
+
+    #[test]
+    fn callers() {
+        let t = Cell::new(0);
+        let mut a = Client::<G, Ping, _>::new(|| t.get(), 1, 1000);
+        let mut b = Client::<G, Ping, _>::new(|| t.get(), 4, 1000);
+
+        // same call from 2 callers, different ids
+        let fa: CanFrame = a.call(2, &Ping).unwrap();
+        let fb: CanFrame = b.call(2, &Ping).unwrap();
+        assert_ne!(fa.id(), fb.id());
+
+        let mut bus = VecDeque::from([fa, fb]);
+        serve(&mut bus);
+        let (ra, rb) = (bus.pop_front().unwrap(), bus.pop_front().unwrap());
+
+        // response to the other caller
+        assert_eq!(b.on_frame(&ra), Ok(None));
+        assert_eq!(a.on_frame(&rb), Ok(None));
+        assert_eq!(a.on_frame(&ra), Ok(Some((2, Pong(0x55aa)))));
+        assert_eq!(b.on_frame(&rb), Ok(Some((2, Pong(0x55aa)))));
+    }
+
+    #[test]
+    fn timeout() {
+        let t = Cell::new(0);
+        let mut c = Client::<G, Ping, _, 2>::new(|| t.get(), 1, 1000);
+        let mut bus = VecDeque::new();
+
+        bus.push_back(c.call(2, &Ping).unwrap());
+        t.set(500);
+        let _: CanFrame = c.call(5, &Ping).unwrap();
+        assert_eq!(c.call::<CanFrame>(6, &Ping), Err(Error::Busy(6)));
+        assert_eq!(c.poll_at(), Some(1000));
+
+        t.set(1000);
+        assert_eq!(c.poll(), Err(Error::Timeout(2)));
+        assert_eq!(c.poll(), Ok(()));
+
+        // late response
+        serve(&mut bus);
+        assert_eq!(c.on_frame(&bus.pop_front().unwrap()), Ok(None));
+
+        t.set(1500);
+        assert_eq!(c.poll(), Err(Error::Timeout(5)));
+        assert_eq!(c.poll_at(), None);
+    }
+
+    #[test]
+    fn other_frames() {
+        let t = Cell::new(0);
+        let mut c = Client::<G, Ping, _>::new(|| t.get(), 1, 1000);
+        let req: CanFrame = c.call(2, &Ping).unwrap();
+
+        // request not for node 3, other msgs & groups
+        assert_eq!(Server::<G, Ping>::on_frame(3, &req), Ok(None));
+        assert_eq!(Server::<G, GetParam>::on_frame(2, &req), Ok(None));
+        assert_eq!(c.on_frame(&req), Ok(None));
+        let f: CanFrame = NodeGroup::<T, 0x1_9876_200, 3, 3, 0, true>::encode_to(1, 2, &Pong(1)).unwrap();
+        assert_eq!(c.on_frame(&f), Ok(None));
+
+        // bad payload
+        let f: CanFrame = G::encode_to(1, 2, &Pong(0)).unwrap();
+        let f = CanFrame::new(f.id(), &f.data()[..1]).unwrap();
+        assert!(matches!(c.on_frame(&f), Err(Error::NodeGroupErr(node_group::Error::SerdeErr(_)))));
+        assert!(c.is_pending(2));
+    }
+}
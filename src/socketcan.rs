@@ -0,0 +1,178 @@
+//! send & receive serde_can messages over Linux SocketCAN
+//!
+//! Works with both `CanSocket` and `CanFdSocket`, classic frames are sent,
+//! and received FD frames of more than 8 bytes are rejected, as a serde_can
+//! value is at most 8 bytes.
+
+use ::socketcan::{CanFrame, EmbeddedFrame, Socket, SocketOptions};
+use embedded_can::Id;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{from_frame, to_frame};
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error {
+    #[error("io error: {0:?}")]
+    IoErr(std::io::ErrorKind),
+    #[error("frame error: {0}")]
+    FrameErr(crate::frame::Error),
+    #[error("frame data of {0} bytes, a serde_can value is at most 8 bytes")]
+    DataTooLong(usize),
+    #[cfg(feature = "node-group")]
+    #[error("node group error: {0}")]
+    NodeGroupErr(crate::node_group::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self { Error::IoErr(e.kind()) }
+}
+
+// flags of `can_id', from linux/can.h
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+const CAN_EFF_MASK: u32 = 0x1fff_ffff;
+#[cfg(feature = "node-group")]
+const CAN_SFF_MASK: u32 = 0x7ff;
+
+pub fn send<T: Serialize, S: Socket, I: Into<Id>>(socket: &S, id: I, x: &T) -> Result<(), Error>
+where CanFrame: Into<S::FrameType>
+{
+    let f: CanFrame = to_frame(id, x).map_err(Error::FrameErr)?;
+    socket.write_frame(&f)?;
+    Ok(())
+}
+
+/// receive next frame, return its id & decoded message
+pub fn recv<T: DeserializeOwned, S: Socket>(socket: &S) -> Result<(Id, T), Error>
+where S::FrameType: EmbeddedFrame
+{
+    let f = socket.read_frame()?;
+    check_len(&f)?;
+    let x = from_frame(&f).map_err(Error::FrameErr)?;
+    Ok((f.id(), x))
+}
+
+// FD frames carry up to 64 bytes, don't silently drop the tail
+fn check_len<F: EmbeddedFrame>(f: &F) -> Result<(), Error> {
+    match f.data().len() {
+        n if n > 8 => Err(Error::DataTooLong(n)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "node-group")]
+pub use node_group::kernel_filter;
+
+#[cfg(feature = "node-group")]
+mod node_group {
+    use super::*;
+    use crate::node_group::{Elem, List, MaskFilter, NodeGroup, NodeId, Select};
+
+    /// kernel filter `(can_id, can_mask)` of `f`, pass data frames only
+    pub fn kernel_filter(f: &MaskFilter) -> (u32, u32) {
+        let flags = CAN_EFF_FLAG | CAN_RTR_FLAG;
+        match f.ext {
+            true => (f.id | CAN_EFF_FLAG, (f.mask & CAN_EFF_MASK) | flags),
+            false => (f.id & CAN_SFF_MASK, (f.mask & CAN_SFF_MASK) | flags),
+        }
+    }
+
+    impl <L: List, const BASE: u32, const NODE_ID_LEN: usize, const MSG_ID_LEN: usize, const PRIO_LEN: usize, const PEER: bool>
+        NodeGroup<L, BASE, NODE_ID_LEN, MSG_ID_LEN, PRIO_LEN, PEER>
+    {
+        /// kernel filter `(can_id, can_mask)`, pass extended data frames selected by `sel`
+        pub fn socketcan_filter(sel: Select<'_>) -> Result<(u32, u32), Error> {
+            let f = Self::mask_filter_ext(sel).map_err(Error::NodeGroupErr)?;
+            Ok(kernel_filter(&f))
+        }
+
+        /// only receive frames selected by `sel` on `socket`
+        pub fn set_socketcan_filter<S: SocketOptions>(socket: &S, sel: Select<'_>) -> Result<(), Error> {
+            socket.set_filters(&[Self::socketcan_filter(sel)?])?;
+            Ok(())
+        }
+
+        pub fn send<S: Socket, X: Serialize + Elem<L>>(socket: &S, node_id: NodeId, x: &X) -> Result<(), Error>
+        where CanFrame: Into<S::FrameType>
+        {
+            let f: CanFrame = Self::encode_ext(node_id, x).map_err(Error::NodeGroupErr)?;
+            socket.write_frame(&f)?;
+            Ok(())
+        }
+
+        /// receive until next message `T` of this group, other frames are dropped
+        pub fn recv<T: DeserializeOwned + Elem<L>, S: Socket>(socket: &S) -> Result<(NodeId, T), Error>
+        where S::FrameType: EmbeddedFrame
+        {
+            use crate::node_group::Error as E;
+
+            loop {
+                let f = socket.read_frame()?;
+                if f.is_remote_frame() { continue }
+
+                match Self::decode::<T, _>(&f) {
+                    Err(E::DecNodeGroupMismatch) | Err(E::DecMsgIdMismatch(_, _)) => continue,
+                    r => { check_len(&f)?; return r.map_err(Error::NodeGroupErr) }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::socketcan::{CanFdSocket, CanSocket};
+
+    #[cfg(feature = "node-group")]
+    use crate::node_group::{Cons, Elem, MaskFilter, Nil, NodeGroup, Select};
+    #[cfg(feature = "node-group")]
+    crate::node_group_msg_def!(T, [u8, u16]);
+    #[cfg(feature = "node-group")]
+    type G = NodeGroup<T, 0x1_9876_540, 3, 3>;
+
+    #[cfg(feature = "node-group")]
+    #[test]
+    fn filter() {
+        assert_eq!(G::socketcan_filter(Select::Group), Ok((0x8000_0000 | 0x1_9876_540, 0xdfff_ffc0)));
+        assert_eq!(G::socketcan_filter(Select::Msg(G::msg_id::<u16>())), Ok((0x8000_0000 | 0x1_9876_541, 0xdfff_ffc7)));
+        assert_eq!(kernel_filter(&MaskFilter { id: 0x123, mask: 0x7f0, ext: false }), (0x123, 0xc000_07f0));
+    }
+
+    #[test]
+    fn fd_too_long() {
+        let id = embedded_can::StandardId::new(0x123).unwrap();
+        let f = ::socketcan::CanFdFrame::new(id, &[0; 12]).unwrap();
+        assert_eq!(check_len(&f), Err(Error::DataTooLong(12)));
+        let f = ::socketcan::CanFdFrame::new(id, &[0; 8]).unwrap();
+        assert_eq!(check_len(&f), Ok(()));
+    }
+
+    // need `vcan0', e.g.
+    //   ip link add dev vcan0 type vcan && ip link set up vcan0
+    #[test]
+    #[ignore]
+    fn vcan() {
+        let tx = CanSocket::open("vcan0").unwrap();
+        let rx = CanFdSocket::open("vcan0").unwrap();
+
+        let id = embedded_can::StandardId::new(0x123).unwrap();
+        send(&tx, id, &(1u8, -2i16)).unwrap();
+        assert_eq!(recv::<(u8, i16), _>(&rx), Ok((Id::Standard(id), (1, -2))));
+    }
+
+    #[cfg(feature = "node-group")]
+    #[test]
+    #[ignore]
+    fn vcan_node_group() {
+        let tx = CanSocket::open("vcan0").unwrap();
+        let rx = CanSocket::open("vcan0").unwrap();
+        G::set_socketcan_filter(&rx, Select::Group).unwrap();
+
+        send(&tx, embedded_can::StandardId::new(0x123).unwrap(), &0u8).unwrap();
+        G::send(&tx, 2, &7u8).unwrap();
+        G::send(&tx, 5, &0x1234u16).unwrap();
+
+        assert_eq!(G::recv::<u16, _>(&rx), Ok((5, 0x1234)));
+    }
+}
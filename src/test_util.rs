@@ -0,0 +1,29 @@
+//! helpers shared by tests of the protocol modules
+
+extern crate std;
+use core::cell::Cell;
+use std::vec::Vec;
+
+/// bytes 0, 1, 2 ... of `len`
+pub fn payload(len: usize) -> Vec<u8> {
+    (0..len).map(|x| x as u8).collect()
+}
+
+/// `Clock` reading `t`
+pub fn clock(t: &Cell<u64>) -> impl Fn() -> u64 + Copy + '_ {
+    move || t.get()
+}
+
+// in-memory bus, call `step` every `tick` until it sends nothing for `quiet`,
+// `step` returns if any frame is sent
+pub fn run<E>(t: &Cell<u64>, tick: u64, quiet: u64, mut step: impl FnMut() -> Result<bool, E>) -> Result<(), E> {
+    let mut idle = 0;
+
+    for _ in 0..1_000_000 {
+        idle = if step()? { 0 } else { idle + tick };
+        if idle >= quiet { return Ok(()) }
+        t.set(t.get() + tick);
+    }
+
+    panic!("not finish");
+}
@@ -0,0 +1,362 @@
+//! typed send & receive over `embedded_can::blocking::Can` and `embedded_can::nb::Can`,
+//! or `asynch::Can` with `async` feature
+
+use embedded_can::{Frame, Id};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{from_frame, to_frame};
+
+#[derive(Debug, thiserror_no_std::Error, PartialEq)]
+pub enum Error<E: embedded_can::Error> {
+    #[error("can error: {0:?}")]
+    CanErr(E),
+    #[error("frame error: {0}")]
+    FrameErr(crate::frame::Error),
+    #[error("serde error: {0}")]
+    SerdeErr(crate::Error),
+    #[cfg(feature = "node-group")]
+    #[error("node group error: {0}")]
+    NodeGroupErr(crate::node_group::Error),
+}
+
+impl <E: embedded_can::Error> From<crate::frame::Error> for Error<E> {
+    fn from(e: crate::frame::Error) -> Self {
+        match e {
+            crate::frame::Error::SerdeErr(e) => Error::SerdeErr(e),
+            _ => Error::FrameErr(e),
+        }
+    }
+}
+
+#[cfg(feature = "node-group")]
+impl <E: embedded_can::Error> From<crate::node_group::Error> for Error<E> {
+    fn from(e: crate::node_group::Error) -> Self {
+        use crate::node_group::Error as G;
+        match e {
+            G::SerdeErr(e) => Error::SerdeErr(e),
+            G::FrameErr(e) => Error::FrameErr(e),
+            _ => Error::NodeGroupErr(e),
+        }
+    }
+}
+
+fn decode<T: DeserializeOwned, F: Frame, E: embedded_can::Error>(f: &F) -> Result<(Id, T), Error<E>> {
+    Ok((f.id(), from_frame(f)?))
+}
+
+pub mod blocking {
+    use super::*;
+    use embedded_can::blocking::Can;
+
+    /// block until `x` is put in transmit buffer
+    pub fn transmit<C: Can, T: Serialize, I: Into<Id>>(can: &mut C, id: I, x: &T) -> Result<(), Error<C::Error>> {
+        let f: C::Frame = to_frame(id, x)?;
+        can.transmit(&f).map_err(Error::CanErr)
+    }
+
+    /// block until next frame, return its id & decoded message
+    pub fn receive<C: Can, T: DeserializeOwned>(can: &mut C) -> Result<(Id, T), Error<C::Error>> {
+        let f = can.receive().map_err(Error::CanErr)?;
+        decode(&f)
+    }
+}
+
+pub mod nb {
+    use super::*;
+    use embedded_can::nb::Can;
+
+    fn lift<E: embedded_can::Error>(e: ::nb::Error<E>) -> ::nb::Error<Error<E>> {
+        e.map(Error::CanErr)
+    }
+
+    /// return replaced lower priority frame, as `embedded_can::nb::Can::transmit`
+    pub fn transmit<C: Can, T: Serialize, I: Into<Id>>(can: &mut C, id: I, x: &T)
+                                                       -> ::nb::Result<Option<C::Frame>, Error<C::Error>> {
+        let f: C::Frame = to_frame(id, x).map_err(Error::from)?;
+        can.transmit(&f).map_err(lift)
+    }
+
+    pub fn receive<C: Can, T: DeserializeOwned>(can: &mut C) -> ::nb::Result<(Id, T), Error<C::Error>> {
+        let f = can.receive().map_err(lift)?;
+        Ok(decode(&f)?)
+    }
+}
+
+#[cfg(feature = "async")]
+pub mod asynch {
+    use super::*;
+
+    /// async CAN interface, for embassy style drivers
+    #[allow(async_fn_in_trait)]
+    pub trait Can {
+        type Frame: Frame;
+        type Error: embedded_can::Error;
+
+        /// wait until `frame` is put in transmit buffer
+        async fn transmit(&mut self, frame: &Self::Frame) -> Result<(), Self::Error>;
+
+        /// wait until a frame is received
+        async fn receive(&mut self) -> Result<Self::Frame, Self::Error>;
+    }
+
+    impl <C: Can> Can for &mut C {
+        type Frame = C::Frame;
+        type Error = C::Error;
+
+        async fn transmit(&mut self, frame: &Self::Frame) -> Result<(), Self::Error> {
+            (**self).transmit(frame).await
+        }
+
+        async fn receive(&mut self) -> Result<Self::Frame, Self::Error> {
+            (**self).receive().await
+        }
+    }
+
+    pub async fn send<C: Can, T: Serialize, I: Into<Id>>(can: &mut C, id: I, x: &T) -> Result<(), Error<C::Error>> {
+        let f: C::Frame = to_frame(id, x)?;
+        can.transmit(&f).await.map_err(Error::CanErr)
+    }
+
+    /// wait for next frame, return its id & decoded message
+    pub async fn recv<C: Can, T: DeserializeOwned>(can: &mut C) -> Result<(Id, T), Error<C::Error>> {
+        let f = can.receive().await.map_err(Error::CanErr)?;
+        decode(&f)
+    }
+}
+
+#[cfg(feature = "node-group")]
+mod node_group {
+    use super::*;
+    use crate::node_group::{Elem, List, NodeGroup, NodeId, Error as E};
+
+    impl <L: List, const BASE: u32, const NODE_ID_LEN: usize, const MSG_ID_LEN: usize, const PRIO_LEN: usize, const PEER: bool>
+        NodeGroup<L, BASE, NODE_ID_LEN, MSG_ID_LEN, PRIO_LEN, PEER>
+    {
+        /// block until `x` is put in transmit buffer
+        pub fn transmit<C, X>(can: &mut C, node_id: NodeId, x: &X) -> Result<(), Error<C::Error>>
+        where C: embedded_can::blocking::Can, X: Serialize + Elem<L>
+        {
+            let f: C::Frame = Self::encode_ext(node_id, x)?;
+            can.transmit(&f).map_err(Error::CanErr)
+        }
+
+        /// block until next message `T` of this group, other frames are dropped
+        pub fn receive<T, C>(can: &mut C) -> Result<(NodeId, T), Error<C::Error>>
+        where C: embedded_can::blocking::Can, T: DeserializeOwned + Elem<L>
+        {
+            loop {
+                let f = can.receive().map_err(Error::CanErr)?;
+                if f.is_remote_frame() { continue }
+
+                match Self::decode::<T, _>(&f) {
+                    Err(E::DecNodeGroupMismatch) | Err(E::DecMsgIdMismatch(_, _)) => continue,
+                    r => return Ok(r?),
+                }
+            }
+        }
+
+        pub fn transmit_nb<C, X>(can: &mut C, node_id: NodeId, x: &X) -> ::nb::Result<Option<C::Frame>, Error<C::Error>>
+        where C: embedded_can::nb::Can, X: Serialize + Elem<L>
+        {
+            let f: C::Frame = Self::encode_ext(node_id, x).map_err(Error::from)?;
+            can.transmit(&f).map_err(|e| e.map(Error::CanErr))
+        }
+
+        /// drop frames not message `T` of this group, until `WouldBlock`
+        pub fn receive_nb<T, C>(can: &mut C) -> ::nb::Result<(NodeId, T), Error<C::Error>>
+        where C: embedded_can::nb::Can, T: DeserializeOwned + Elem<L>
+        {
+            loop {
+                let f = can.receive().map_err(|e| e.map(Error::CanErr))?;
+                if f.is_remote_frame() { continue }
+
+                match Self::decode::<T, _>(&f) {
+                    Err(E::DecNodeGroupMismatch) | Err(E::DecMsgIdMismatch(_, _)) => continue,
+                    r => return Ok(r.map_err(Error::from)?),
+                }
+            }
+        }
+
+        #[cfg(feature = "async")]
+        pub async fn send_async<C, X>(can: &mut C, node_id: NodeId, x: &X) -> Result<(), Error<C::Error>>
+        where C: asynch::Can, X: Serialize + Elem<L>
+        {
+            let f: C::Frame = Self::encode_ext(node_id, x)?;
+            can.transmit(&f).await.map_err(Error::CanErr)
+        }
+
+        /// wait for next message `T` of this group, other frames are dropped
+        #[cfg(feature = "async")]
+        pub async fn recv_async<T, C>(can: &mut C) -> Result<(NodeId, T), Error<C::Error>>
+        where C: asynch::Can, T: DeserializeOwned + Elem<L>
+        {
+            loop {
+                let f = can.receive().await.map_err(Error::CanErr)?;
+                if f.is_remote_frame() { continue }
+
+                match Self::decode::<T, _>(&f) {
+                    Err(E::DecNodeGroupMismatch) | Err(E::DecMsgIdMismatch(_, _)) => continue,
+                    r => return Ok(r?),
+                }
+            }
+        }
+
+        /// endless stream of message `T` of this group, as `recv_async`
+        #[cfg(feature = "async")]
+        pub fn recv_stream<T, C>(can: C) -> impl futures_util::Stream<Item = Result<(NodeId, T), Error<C::Error>>>
+        where C: asynch::Can, T: DeserializeOwned + Elem<L>
+        {
+            futures_util::stream::unfold(can, |mut can| async move {
+                let r = Self::recv_async::<T, C>(&mut can).await;
+                Some((r, can))
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::CanFrame;
+    use core::convert::Infallible;
+    use embedded_can::StandardId;
+    use heapless::Deque;
+
+    // loopback, transmitted frames are received in order
+    #[derive(Default)]
+    struct Bus { q: Deque<CanFrame, 8> }
+
+    impl embedded_can::blocking::Can for Bus {
+        type Frame = CanFrame;
+        type Error = Infallible;
+
+        fn transmit(&mut self, f: &CanFrame) -> Result<(), Infallible> {
+            self.q.push_back(*f).unwrap();
+            Ok(())
+        }
+
+        fn receive(&mut self) -> Result<CanFrame, Infallible> {
+            Ok(self.q.pop_front().unwrap())
+        }
+    }
+
+    impl embedded_can::nb::Can for Bus {
+        type Frame = CanFrame;
+        type Error = Infallible;
+
+        fn transmit(&mut self, f: &CanFrame) -> ::nb::Result<Option<CanFrame>, Infallible> {
+            self.q.push_back(*f).map_err(|_| ::nb::Error::WouldBlock)?;
+            Ok(None)
+        }
+
+        fn receive(&mut self) -> ::nb::Result<CanFrame, Infallible> {
+            self.q.pop_front().ok_or(::nb::Error::WouldBlock)
+        }
+    }
+
+    #[test]
+    fn blocking() {
+        let mut bus = Bus::default();
+        let id = StandardId::new(0x123).unwrap();
+
+        blocking::transmit(&mut bus, id, &(1u8, -2i16)).unwrap();
+        assert_eq!(blocking::receive(&mut bus), Ok((Id::Standard(id), (1u8, -2i16))));
+
+        assert_eq!(blocking::transmit(&mut bus, id, &[0u32; 3]), Err(Error::SerdeErr(crate::Error::SerMsgTooLong)));
+
+        blocking::transmit(&mut bus, id, &1u8).unwrap();
+        assert_eq!(blocking::receive::<_, u16>(&mut bus), Err(Error::SerdeErr(crate::Error::DeMsgTooLong)));
+    }
+
+    #[test]
+    fn nb() {
+        let mut bus = Bus::default();
+        let id = StandardId::new(0x123).unwrap();
+
+        assert_eq!(nb::receive::<_, u8>(&mut bus), Err(::nb::Error::WouldBlock));
+
+        for i in 0..8u8 {
+            assert_eq!(nb::transmit(&mut bus, id, &i), Ok(None));
+        }
+        assert_eq!(nb::transmit(&mut bus, id, &8u8), Err(::nb::Error::WouldBlock));
+
+        assert_eq!(nb::receive(&mut bus), Ok((Id::Standard(id), 0u8)));
+    }
+
+    #[cfg(feature = "node-group")]
+    #[test]
+    fn node_group() {
+        use crate::node_group::{Cons, Elem, Nil, NodeGroup};
+        crate::node_group_msg_def!(T, [i8, u16]);
+        type G = NodeGroup<T, 0x1_9876_540, 3, 3>;
+
+        let mut bus = Bus::default();
+        blocking::transmit(&mut bus, StandardId::new(0x123).unwrap(), &0u8).unwrap();
+        G::transmit(&mut bus, 2, &7i8).unwrap();
+        G::transmit(&mut bus, 5, &0x1234u16).unwrap();
+        assert_eq!(G::receive::<u16, _>(&mut bus), Ok((5, 0x1234)));
+
+        assert_eq!(G::transmit(&mut bus, 8, &7i8), Err(Error::NodeGroupErr(crate::node_group::Error::EncNodeIdOutOfRange(8, 3))));
+
+        G::transmit_nb(&mut bus, 1, &3i8).unwrap();
+        G::transmit_nb(&mut bus, 1, &3u16).unwrap();
+        assert_eq!(G::receive_nb::<u16, _>(&mut bus), Ok((1, 3)));
+        assert_eq!(G::receive_nb::<u16, _>(&mut bus), Err(::nb::Error::WouldBlock));
+    }
+
+    #[cfg(feature = "async")]
+    impl asynch::Can for Bus {
+        type Frame = CanFrame;
+        type Error = Infallible;
+
+        async fn transmit(&mut self, f: &CanFrame) -> Result<(), Infallible> {
+            self.q.push_back(*f).unwrap();
+            Ok(())
+        }
+
+        async fn receive(&mut self) -> Result<CanFrame, Infallible> {
+            Ok(self.q.pop_front().unwrap())
+        }
+    }
+
+    // all futures here are ready at first poll
+    #[cfg(feature = "async")]
+    fn block_on<F: core::future::Future>(f: F) -> F::Output {
+        use core::task::{Context, Poll, Waker};
+
+        let mut f = core::pin::pin!(f);
+        let mut cx = Context::from_waker(Waker::noop());
+        match f.as_mut().poll(&mut cx) {
+            Poll::Ready(r) => r,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        let mut bus = Bus::default();
+        let id = StandardId::new(0x123).unwrap();
+
+        block_on(asynch::send(&mut bus, id, &(1u8, -2i16))).unwrap();
+        assert_eq!(block_on(asynch::recv(&mut bus)), Ok((Id::Standard(id), (1u8, -2i16))));
+    }
+
+    #[cfg(all(feature = "async", feature = "node-group"))]
+    #[test]
+    fn asynch_node_group() {
+        use crate::node_group::{Cons, Elem, Nil, NodeGroup};
+        use futures_util::StreamExt;
+        crate::node_group_msg_def!(T, [u16, i16]);
+        type G = NodeGroup<T, 0x1_9876_540, 3, 3>;
+
+        let mut bus = Bus::default();
+        block_on(G::send_async(&mut bus, 2, &7u16)).unwrap();
+        block_on(G::send_async(&mut bus, 5, &-2i16)).unwrap();
+        block_on(G::send_async(&mut bus, 6, &-3i16)).unwrap();
+        assert_eq!(block_on(G::recv_async::<i16, _>(&mut bus)), Ok((5, -2)));
+
+        let mut s = core::pin::pin!(G::recv_stream::<i16, _>(&mut bus));
+        assert_eq!(block_on(s.next()), Some(Ok((6, -3))));
+    }
+}